@@ -0,0 +1,26 @@
+// Inputs shared by every mesh shader, `#import "mesh.wgsl"` to use them.
+
+struct UniformBufferObject {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> ubo: UniformBufferObject;
+
+struct VertexInput {
+	@location(0) position: vec3<f32>,
+	@location(1) color: vec3<f32>,
+	@location(2) tex_coords: vec2<f32>,
+	@location(3) normal: vec3<f32>
+}
+
+struct ModelInput {
+    @location(5) x: vec4<f32>,
+    @location(6) y: vec4<f32>,
+    @location(7) z: vec4<f32>,
+    @location(8) w: vec4<f32>,
+};
+
+fn model_matrix(m: ModelInput) -> mat4x4<f32> {
+    return mat4x4<f32>(m.x, m.y, m.z, m.w);
+}
@@ -0,0 +1,25 @@
+// Output of the `fragment_oit` entry point of transparent materials, drawn by
+// cameras using weighted blended transparency. `#import "oit.wgsl"` to use it.
+
+struct OitOutput {
+    @location(0) accum: vec4<f32>,
+    @location(1) revealage: f32,
+};
+
+// Weights a non-premultiplied color by its alpha and depth, `frag_coord` is
+// the fragment's `@builtin(position)`. Closer and more opaque fragments weigh
+// more, following equation 10 of McGuire and Bavoil.
+fn oit_output(color: vec4<f32>, frag_coord: vec4<f32>) -> OitOutput {
+    let alpha = clamp(color.a, 0.0, 1.0);
+    let weight = clamp(
+        pow(min(1.0, alpha * 10.0) + 0.01, 3.0) * 1e8 * pow(1.0 - frag_coord.z * 0.9, 3.0),
+        1e-2,
+        3e3,
+    );
+
+    var out: OitOutput;
+    out.accum = vec4<f32>(color.rgb * alpha, alpha) * weight;
+    out.revealage = alpha;
+
+    return out;
+}
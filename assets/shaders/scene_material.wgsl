@@ -0,0 +1,66 @@
+#import "mesh.wgsl"
+#import "oit.wgsl"
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+struct SceneMaterial {
+    base_color: vec4<f32>,
+    alpha_cutoff: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: SceneMaterial;
+
+#ifdef BASE_COLOR_TEXTURE
+@group(1) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_color_sampler: sampler;
+#endif
+
+@vertex
+fn vertex(v: VertexInput, m: ModelInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = ubo.view_proj * model_matrix(m) * vec4<f32>(v.position, 1.0);
+    out.color = v.color;
+    out.tex_coords = v.tex_coords;
+
+    return out;
+}
+
+fn base_color(in: VertexOutput) -> vec4<f32> {
+    var color = material.base_color * vec4<f32>(in.color, 1.0);
+
+#ifdef BASE_COLOR_TEXTURE
+    color *= textureSample(base_color_texture, base_color_sampler, in.tex_coords);
+#endif
+
+    return color;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = base_color(in);
+
+#ifdef ALPHA_MASK
+    if color.a < material.alpha_cutoff {
+        discard;
+    }
+#endif
+
+#ifdef ALPHA_OPAQUE
+    color.a = 1.0;
+#endif
+
+    return color;
+}
+
+// Only used by blended materials, for cameras with weighted blended transparency.
+@fragment
+fn fragment_oit(in: VertexOutput) -> OitOutput {
+    return oit_output(base_color(in), in.position);
+}
@@ -1,24 +1,6 @@
 // Vertex shader
 
-struct UniformBufferObject {
-    view_proj: mat4x4<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> ubo: UniformBufferObject;
-
-struct VertexInput {
-	@location(0) position: vec3<f32>,
-	@location(1) color: vec3<f32>,
-	@location(2) tex_coords: vec2<f32>
-}
-
-struct ModelInput {
-    @location(5) x: vec4<f32>,
-    @location(6) y: vec4<f32>,
-    @location(7) z: vec4<f32>,
-    @location(8) w: vec4<f32>,
-};
+#import "mesh.wgsl"
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
@@ -28,7 +10,7 @@ struct VertexOutput {
 
 @vertex
 fn vertex(v: VertexInput, m: ModelInput) -> VertexOutput {
-    let model = mat4x4<f32>(m.x, m.y, m.z, m.w);
+    let model = model_matrix(m);
 
     var out: VertexOutput;
     out.position = ubo.view_proj * model * vec4<f32>(v.position, 1.0);
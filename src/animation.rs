@@ -0,0 +1,509 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+use crate::{
+    asset::{Asset, Assets, Handle},
+    plugin::Plugin,
+    schedule::PostUpdate,
+    time::Time,
+    transform::{propagate_transforms, Transform},
+    AppBuilder,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    // Keyframes store an in-tangent, the value and an out-tangent.
+    CubicSpline,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Keyframes {
+    Translation(Vec<Vec3>),
+    Rotation(Vec<Quat>),
+    Scale(Vec<Vec3>),
+    // One weight per morph target for every keyframe, flattened.
+    Weights(Vec<f32>),
+}
+
+// Animates one property of a scene node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    // Index of the node in its scene, resolved with `AnimationTargets`.
+    pub node: usize,
+    // Increasing keyframe times in seconds.
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+    pub interpolation: Interpolation,
+}
+
+impl Curve {
+    // Values before the first keyframe and after the last one are held.
+    pub fn sample(&self, time: f32) -> NodePose {
+        let mut pose = NodePose::default();
+
+        match &self.keyframes {
+            Keyframes::Translation(values) => {
+                pose.translation = Some(self.sample_values(time, |i| values[i]));
+            }
+            Keyframes::Rotation(values) => {
+                pose.rotation = Some(self.sample_values(time, |i| values[i]).normalize());
+            }
+            Keyframes::Scale(values) => {
+                pose.scale = Some(self.sample_values(time, |i| values[i]));
+            }
+            Keyframes::Weights(values) => {
+                let count = values.len() / self.stored_keyframes().max(1);
+
+                pose.weights = Some(
+                    (0..count)
+                        .map(|target| self.sample_values(time, |i| values[i * count + target]))
+                        .collect(),
+                );
+            }
+        }
+
+        pose
+    }
+
+    // Whether there are keyframes and as many values as times, which sampling
+    // relies on.
+    pub fn is_valid(&self) -> bool {
+        let stored = self.stored_keyframes();
+        let len = match &self.keyframes {
+            Keyframes::Translation(values) | Keyframes::Scale(values) => values.len(),
+            Keyframes::Rotation(values) => values.len(),
+            Keyframes::Weights(values) if stored > 0 && values.len() % stored == 0 => stored,
+            Keyframes::Weights(_) => return false,
+        };
+
+        stored > 0 && len == stored
+    }
+
+    // Number of stored values per morph target, three per keyframe for splines.
+    fn stored_keyframes(&self) -> usize {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.times.len() * 3,
+            _ => self.times.len(),
+        }
+    }
+
+    fn sample_values<T: Animatable>(&self, time: f32, value: impl Fn(usize) -> T) -> T {
+        let cubic = self.interpolation == Interpolation::CubicSpline;
+        let key = |keyframe: usize| match cubic {
+            true => value(keyframe * 3 + 1),
+            false => value(keyframe),
+        };
+
+        let next = self.times.partition_point(|t| *t <= time);
+        if next == 0 {
+            return key(0);
+        }
+        if next == self.times.len() {
+            return key(next - 1);
+        }
+
+        let previous = next - 1;
+        let span = self.times[next] - self.times[previous];
+        let s = (time - self.times[previous]) / span;
+
+        match self.interpolation {
+            Interpolation::Step => key(previous),
+            Interpolation::Linear => T::interpolate(key(previous), key(next), s),
+            // Tangents are per second, scaled by the length of the segment.
+            Interpolation::CubicSpline => T::hermite(
+                key(previous),
+                value(previous * 3 + 2).scale(span),
+                key(next),
+                value(next * 3).scale(span),
+                s,
+            ),
+        }
+    }
+}
+
+trait Animatable: Copy {
+    fn interpolate(a: Self, b: Self, s: f32) -> Self;
+    fn scale(self, factor: f32) -> Self;
+    fn hermite(p0: Self, m0: Self, p1: Self, m1: Self, s: f32) -> Self;
+}
+
+fn hermite_weights(s: f32) -> [f32; 4] {
+    let s2 = s * s;
+    let s3 = s2 * s;
+
+    [
+        2.0 * s3 - 3.0 * s2 + 1.0,
+        s3 - 2.0 * s2 + s,
+        -2.0 * s3 + 3.0 * s2,
+        s3 - s2,
+    ]
+}
+
+macro_rules! impl_animatable {
+    ($($ty:ty),*) => {
+        $(impl Animatable for $ty {
+            fn interpolate(a: Self, b: Self, s: f32) -> Self {
+                a + (b - a) * s
+            }
+
+            fn scale(self, factor: f32) -> Self {
+                self * factor
+            }
+
+            fn hermite(p0: Self, m0: Self, p1: Self, m1: Self, s: f32) -> Self {
+                let [a, b, c, d] = hermite_weights(s);
+                p0 * a + m0 * b + p1 * c + m1 * d
+            }
+        })*
+    };
+}
+
+impl_animatable!(f32, Vec3);
+
+// Splines are evaluated on the components, the caller normalizes the result.
+impl Animatable for Quat {
+    fn interpolate(a: Self, b: Self, s: f32) -> Self {
+        a.slerp(b, s)
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        self * factor
+    }
+
+    fn hermite(p0: Self, m0: Self, p1: Self, m1: Self, s: f32) -> Self {
+        let [a, b, c, d] = hermite_weights(s);
+        Quat::from_vec4(
+            Vec4::from(p0) * a + Vec4::from(m0) * b + Vec4::from(p1) * c + Vec4::from(m1) * d,
+        )
+    }
+}
+
+// Properties of a node set by a clip, None where the clip doesn't animate them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodePose {
+    pub translation: Option<Vec3>,
+    pub rotation: Option<Quat>,
+    pub scale: Option<Vec3>,
+    pub weights: Option<Vec<f32>>,
+}
+
+impl NodePose {
+    fn merge(&mut self, other: NodePose) {
+        self.translation = other.translation.or(self.translation);
+        self.rotation = other.rotation.or(self.rotation);
+        self.scale = other.scale.or(self.scale);
+        self.weights = other.weights.or(self.weights.take());
+    }
+
+    // Moves towards `other` by `s`, properties set on one side only are kept.
+    fn blend(self, other: NodePose, s: f32) -> NodePose {
+        fn mix<T>(a: Option<T>, b: Option<T>, f: impl FnOnce(T, T) -> T) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(f(a, b)),
+                (a, b) => b.or(a),
+            }
+        }
+
+        NodePose {
+            translation: mix(self.translation, other.translation, |a, b| a.lerp(b, s)),
+            rotation: mix(self.rotation, other.rotation, |a, b| a.slerp(b, s)),
+            scale: mix(self.scale, other.scale, |a, b| a.lerp(b, s)),
+            weights: mix(self.weights, other.weights, |a, b| {
+                a.iter().zip(&b).map(|(a, b)| a + (b - a) * s).collect()
+            }),
+        }
+    }
+}
+
+// Curves of a glTF animation. Nodes are indices into the scene it came from.
+pub struct AnimationClip {
+    pub name: Option<String>,
+    pub curves: Vec<Curve>,
+    // Time of the last keyframe.
+    pub duration: f32,
+}
+
+impl Asset for AnimationClip {}
+
+impl AnimationClip {
+    pub fn new(name: Option<String>, curves: Vec<Curve>) -> Self {
+        let duration = curves
+            .iter()
+            .filter_map(|curve| curve.times.last())
+            .fold(0.0, |duration: f32, time| duration.max(*time));
+
+        Self {
+            name,
+            curves,
+            duration,
+        }
+    }
+
+    // Pose of every animated node at `time`.
+    pub fn sample(&self, time: f32) -> HashMap<usize, NodePose> {
+        let mut poses = HashMap::<usize, NodePose>::new();
+
+        for curve in &self.curves {
+            poses
+                .entry(curve.node)
+                .or_default()
+                .merge(curve.sample(time));
+        }
+
+        poses
+    }
+}
+
+// Entity of each node of a spawned scene, by node index. None for nodes that
+// aren't part of the scene.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnimationTargets(pub Vec<Option<Entity>>);
+
+// Morph target weights of a mesh, set directly or by animations. Weights past
+// the mesh's targets are ignored, missing ones are zero.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub struct MorphWeights(pub Vec<f32>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PlayingClip {
+    clip: Handle<AnimationClip>,
+    time: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fade {
+    duration: f32,
+    elapsed: f32,
+}
+
+// Plays clips on the nodes listed in the entity's `AnimationTargets`, usually
+// the root of a spawned scene.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct AnimationPlayer {
+    current: Option<PlayingClip>,
+    // Clip faded out by `cross_fade`.
+    previous: Option<PlayingClip>,
+    fade: Option<Fade>,
+
+    paused: bool,
+    repeat: bool,
+    // Negative speeds play backwards.
+    speed: f32,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self {
+            current: None,
+            previous: None,
+            fade: None,
+            paused: false,
+            repeat: true,
+            speed: 1.0,
+        }
+    }
+}
+
+impl AnimationPlayer {
+    // Starts `clip` from the beginning, stopping any other clip.
+    pub fn play(&mut self, clip: Handle<AnimationClip>) -> &mut Self {
+        self.current = Some(PlayingClip { clip, time: 0.0 });
+        self.previous = None;
+        self.fade = None;
+        self
+    }
+
+    // Starts `clip` from the beginning and blends it in over `duration` seconds
+    // while the current clip keeps playing underneath.
+    pub fn cross_fade(&mut self, clip: Handle<AnimationClip>, duration: f32) -> &mut Self {
+        let previous = self.current.take();
+        self.play(clip);
+
+        if duration > 0.0 && previous.is_some() {
+            self.previous = previous;
+            self.fade = Some(Fade {
+                duration,
+                elapsed: 0.0,
+            });
+        }
+
+        self
+    }
+
+    pub fn stop(&mut self) -> &mut Self {
+        self.current = None;
+        self.previous = None;
+        self.fade = None;
+        self
+    }
+
+    pub fn pause(&mut self) -> &mut Self {
+        self.paused = true;
+        self
+    }
+
+    pub fn unpause(&mut self) -> &mut Self {
+        self.paused = false;
+        self
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Clips loop by default, otherwise they stop on their last frame.
+    pub fn set_repeat(&mut self, repeat: bool) -> &mut Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn set_speed(&mut self, speed: f32) -> &mut Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // Jumps to `time` seconds into the current clip.
+    pub fn seek(&mut self, time: f32) -> &mut Self {
+        if let Some(current) = &mut self.current {
+            current.time = time;
+        }
+
+        self
+    }
+
+    pub fn clip(&self) -> Option<Handle<AnimationClip>> {
+        self.current.map(|current| current.clip)
+    }
+
+    // Time into the current clip.
+    pub fn elapsed(&self) -> f32 {
+        self.current.map_or(0.0, |current| current.time)
+    }
+
+    // Whether a clip that doesn't repeat reached its end.
+    pub fn is_finished(&self, clips: &Assets<AnimationClip>) -> bool {
+        match (self.current, self.repeat) {
+            (Some(current), false) => {
+                clips
+                    .get(current.clip)
+                    .is_some_and(|clip| match self.speed < 0.0 {
+                        true => current.time <= 0.0,
+                        false => current.time >= clip.duration,
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    fn advance(&mut self, delta: f32, clips: &Assets<AnimationClip>) {
+        let (repeat, step) = (self.repeat, delta * self.speed);
+
+        for playing in self.current.iter_mut().chain(self.previous.iter_mut()) {
+            let duration = clips.get(playing.clip).map_or(0.0, |clip| clip.duration);
+
+            playing.time = match (repeat, duration > 0.0) {
+                (_, false) => 0.0,
+                (true, true) => (playing.time + step).rem_euclid(duration),
+                (false, true) => (playing.time + step).clamp(0.0, duration),
+            };
+        }
+
+        // Fades take the same time whatever the speed.
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += delta;
+
+            if fade.elapsed >= fade.duration {
+                self.previous = None;
+                self.fade = None;
+            }
+        }
+    }
+
+    // Blended pose of every animated node, empty until the clips are loaded.
+    fn sample(&self, clips: &Assets<AnimationClip>) -> HashMap<usize, NodePose> {
+        let sample = |playing: Option<PlayingClip>| {
+            playing
+                .and_then(|playing| Some(clips.get(playing.clip)?.sample(playing.time)))
+                .unwrap_or_default()
+        };
+
+        let mut current = sample(self.current);
+
+        let (previous, fade) = match (self.previous, self.fade) {
+            (Some(previous), Some(fade)) => (sample(Some(previous)), fade),
+            _ => return current,
+        };
+
+        let s = (fade.elapsed / fade.duration).clamp(0.0, 1.0);
+
+        for (node, pose) in previous {
+            let blended = match current.remove(&node) {
+                Some(current) => pose.blend(current, s),
+                None => pose,
+            };
+
+            current.insert(node, blended);
+        }
+
+        current
+    }
+}
+
+// Advances every `AnimationPlayer` and writes the pose of its clips to the
+// `Transform` and `MorphWeights` of the targeted nodes.
+pub fn animate(
+    time: Res<Time>,
+    clips: Res<Assets<AnimationClip>>,
+    mut players: Query<(&mut AnimationPlayer, &AnimationTargets)>,
+    mut transforms: Query<&mut Transform>,
+    mut weights: Query<&mut MorphWeights>,
+) {
+    for (mut player, targets) in &mut players {
+        if !player.paused {
+            player.advance(time.delta_seconds(), &clips);
+        }
+
+        for (node, pose) in player.sample(&clips) {
+            let entity = match targets.0.get(node).copied().flatten() {
+                Some(entity) => entity,
+                None => continue,
+            };
+
+            if let Ok(mut transform) = transforms.get_mut(entity) {
+                if let Some(translation) = pose.translation {
+                    transform.translation = translation;
+                }
+                if let Some(rotation) = pose.rotation {
+                    transform.rotation = rotation;
+                }
+                if let Some(scale) = pose.scale {
+                    transform.scale = scale;
+                }
+            }
+
+            if let (Ok(mut morph_weights), Some(pose_weights)) =
+                (weights.get_mut(entity), pose.weights)
+            {
+                morph_weights.0 = pose_weights;
+            }
+        }
+    }
+}
+
+// Plays `AnimationPlayer`s before transforms are propagated.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<AnimationClip>()
+            .add_systems(PostUpdate, animate.before(propagate_transforms));
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use bevy_ecs::{component::TableStorage, prelude::*};
+
+use crate::asset::Asset;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HandleId(pub u64);
+
+impl HandleId {
+    // Unique across every asset type and server.
+    pub fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// Typed reference to an asset in `Assets<T>`, valid before the asset is loaded.
+pub struct Handle<T> {
+    pub id: HandleId,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn new(id: HandleId) -> Self {
+        Self {
+            id,
+            marker: PhantomData,
+        }
+    }
+}
+
+// Implemented by hand since the derives would require `T` to implement them.
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle<{}>({})", std::any::type_name::<T>(), self.id.0)
+    }
+}
+
+impl<T: Asset> Component for Handle<T> {
+    type Storage = TableStorage;
+}
+
+// Loaded assets of one type. Assets are shared so systems can keep them past
+// the frame, e.g. for picking.
+#[derive(Resource)]
+pub struct Assets<T: Asset> {
+    assets: HashMap<HandleId, Arc<T>>,
+}
+
+impl<T: Asset> Default for Assets<T> {
+    fn default() -> Self {
+        Self {
+            assets: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Asset> Assets<T> {
+    pub fn get(&self, handle: Handle<T>) -> Option<&Arc<T>> {
+        self.assets.get(&handle.id)
+    }
+
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.assets.contains_key(&handle.id)
+    }
+
+    pub fn insert(&mut self, handle: Handle<T>, asset: impl Into<Arc<T>>) {
+        self.assets.insert(handle.id, asset.into());
+    }
+
+    // Stores an asset that wasn't loaded from a file under a new handle.
+    pub fn add(&mut self, asset: impl Into<Arc<T>>) -> Handle<T> {
+        let handle = Handle::new(HandleId::next());
+        self.assets.insert(handle.id, asset.into());
+
+        handle
+    }
+
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<Arc<T>> {
+        self.assets.remove(&handle.id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle<T>, &Arc<T>)> {
+        self.assets
+            .iter()
+            .map(|(id, asset)| (Handle::new(*id), asset))
+    }
+}
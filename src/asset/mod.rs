@@ -0,0 +1,51 @@
+pub mod handle;
+pub mod server;
+pub mod thread_pool;
+
+pub use handle::{Assets, Handle, HandleId};
+pub use server::{AssetEvent, AssetServer, LoadState};
+
+use std::path::Path;
+
+use crate::{loader::MeshData, plugin::Plugin, schedule::PreUpdate, AppBuilder};
+
+// Data stored in `Assets<T>`. Assets loaded from files by the `AssetServer` are
+// decoded on one of its worker threads, others are only created in code with
+// `Assets::add`.
+pub trait Asset: Sized + Send + Sync + 'static {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        anyhow::bail!(
+            "{} can't be loaded from {}",
+            std::any::type_name::<Self>(),
+            path.display()
+        )
+    }
+}
+
+impl Asset for image::DynamicImage {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(image::open(path)?)
+    }
+}
+
+impl AppBuilder {
+    // Registers the storage and events of an asset type. Adding it twice does
+    // nothing.
+    pub fn add_asset<T: Asset>(&mut self) -> &mut Self {
+        self.init_resource::<Assets<T>>()
+            .add_event::<AssetEvent<T>>()
+    }
+}
+
+// Inserts the `AssetServer` and registers meshes and textures, scenes are
+// registered by `ScenePlugin`.
+pub struct AssetPlugin;
+
+impl Plugin for AssetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AssetServer>()
+            .add_asset::<MeshData>()
+            .add_asset::<image::DynamicImage>()
+            .add_systems(PreUpdate, server::update_assets);
+    }
+}
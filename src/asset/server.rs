@@ -0,0 +1,264 @@
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use bevy_ecs::prelude::*;
+
+use crate::{
+    asset::{
+        handle::{Assets, Handle, HandleId},
+        thread_pool::ThreadPool,
+        Asset,
+    },
+    watcher::FileWatcher,
+};
+
+pub const ASSET_DIR: &str = "assets";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadState {
+    // The handle wasn't returned by this server.
+    NotLoaded,
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+// Sent when an asset is stored in its `Assets<T>`, for the first time or
+// again after its file changed.
+#[derive(Event)]
+pub enum AssetEvent<T: Asset> {
+    Loaded(Handle<T>),
+    Modified(Handle<T>),
+}
+
+// Stores a decoded asset in its `Assets<T>`, run on the main thread.
+type Insert = Box<dyn FnOnce(&mut World) + Send>;
+
+// Queues the load of an asset of the type it was created for.
+type Spawn = fn(&AssetServer, HandleId, PathBuf);
+
+struct Loaded {
+    id: HandleId,
+    path: PathBuf,
+    result: Result<Insert, String>,
+}
+
+struct Entry {
+    path: PathBuf,
+    state: LoadState,
+    spawn: Spawn,
+}
+
+#[derive(Default)]
+struct Entries {
+    handles: HashMap<(TypeId, PathBuf), HandleId>,
+    entries: HashMap<HandleId, Entry>,
+}
+
+// Loads assets on a thread pool. `load` returns a handle right away and the
+// asset shows up in `Assets<T>` once `update_assets` sees it finished, loading
+// the same path as the same type again returns the same handle. Paths are
+// relative to the root directory. In debug builds loaded files are watched and
+// loaded again when they change, keeping their handles.
+#[derive(Resource)]
+pub struct AssetServer {
+    root: PathBuf,
+    pool: ThreadPool,
+    entries: Mutex<Entries>,
+    sender: mpsc::Sender<Loaded>,
+    receiver: Mutex<mpsc::Receiver<Loaded>>,
+    watcher: Option<Mutex<FileWatcher>>,
+}
+
+impl AssetServer {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get().min(4));
+        let (sender, receiver) = mpsc::channel();
+
+        let watcher = match cfg!(debug_assertions) {
+            true => FileWatcher::new()
+                .map_err(|err| log::warn!("Asset hot reloading disabled: {}", err))
+                .ok()
+                .map(Mutex::new),
+            false => None,
+        };
+
+        Self {
+            root: root.into(),
+            pool: ThreadPool::new(threads),
+            entries: Mutex::new(Entries::default()),
+            sender,
+            receiver: Mutex::new(receiver),
+            watcher,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Assets that failed to load are retried.
+    pub fn load<T: Asset>(&self, path: impl AsRef<Path>) -> Handle<T> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = self.entries.lock().unwrap();
+
+        let key = (TypeId::of::<T>(), path.clone());
+        let id = match entries.handles.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = HandleId::next();
+                entries.handles.insert(key, id);
+                id
+            }
+        };
+
+        let entry = entries.entries.entry(id).or_insert_with(|| Entry {
+            path: path.clone(),
+            state: LoadState::NotLoaded,
+            spawn: Self::spawn_load::<T>,
+        });
+
+        if matches!(entry.state, LoadState::NotLoaded | LoadState::Failed(_)) {
+            entry.state = LoadState::Loading;
+            self.spawn_load::<T>(id, path);
+        }
+
+        Handle::new(id)
+    }
+
+    pub fn load_state<T>(&self, handle: Handle<T>) -> LoadState {
+        self.entries
+            .lock()
+            .unwrap()
+            .entries
+            .get(&handle.id)
+            .map_or(LoadState::NotLoaded, |entry| entry.state.clone())
+    }
+
+    pub fn is_loaded<T>(&self, handle: Handle<T>) -> bool {
+        self.load_state(handle) == LoadState::Loaded
+    }
+
+    fn spawn_load<T: Asset>(&self, id: HandleId, path: PathBuf) {
+        let file = self.root.join(&path);
+        let sender = self.sender.clone();
+
+        if let Some(watcher) = &self.watcher {
+            watcher.lock().unwrap().watch(&file);
+        }
+
+        self.pool.execute(move || {
+            // A panicking loader fails its asset instead of taking down the worker.
+            let result = match panic::catch_unwind(AssertUnwindSafe(|| T::load(&file))) {
+                Ok(Ok(asset)) => Ok(Box::new(move |world: &mut World| {
+                    let handle = Handle::new(id);
+                    let mut assets = world.get_resource_or_insert_with(Assets::<T>::default);
+
+                    let event = match assets.contains(handle) {
+                        true => AssetEvent::Modified(handle),
+                        false => AssetEvent::Loaded(handle),
+                    };
+
+                    assets.insert(handle, asset);
+
+                    if let Some(mut events) = world.get_resource_mut::<Events<AssetEvent<T>>>() {
+                        events.send(event);
+                    }
+                }) as Insert),
+                Ok(Err(err)) => Err(format!("{:#}", err)),
+                Err(_) => Err("Loader panicked".to_string()),
+            };
+
+            // The receiver only goes away with the server.
+            let _ = sender.send(Loaded { id, path, result });
+        });
+    }
+
+    // Finished loads, in the order they completed.
+    fn finished(&self) -> Vec<Loaded> {
+        self.receiver.lock().unwrap().try_iter().collect()
+    }
+
+    // Loads the assets whose file changed again. Until they finish, the
+    // previous version stays in `Assets<T>`.
+    fn reload_changed(&self) {
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.lock().unwrap().changed(),
+            None => return,
+        };
+
+        let entries = self.entries.lock().unwrap();
+
+        for file in changed {
+            let path = match file.strip_prefix(&self.root) {
+                Ok(path) => path,
+                Err(_) => continue,
+            };
+
+            for (id, entry) in &entries.entries {
+                if entry.path == path && entry.state != LoadState::Loading {
+                    log::info!("Reloading {}", path.display());
+                    (entry.spawn)(self, *id, entry.path.clone());
+                }
+            }
+        }
+    }
+
+    // A failed reload keeps the asset loaded with its previous version.
+    fn finish(&self, id: HandleId, path: &Path, error: Option<String>) {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = match entries.entries.get_mut(&id) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        match error {
+            None => entry.state = LoadState::Loaded,
+            Some(err) if entry.state == LoadState::Loaded => {
+                log::error!("Failed to reload {}: {}", path.display(), err);
+            }
+            Some(err) => {
+                log::error!("Failed to load {}: {}", path.display(), err);
+                entry.state = LoadState::Failed(err);
+            }
+        }
+    }
+}
+
+impl Default for AssetServer {
+    fn default() -> Self {
+        Self::new(ASSET_DIR)
+    }
+}
+
+// Moves the assets finished since the last frame into their `Assets<T>` and
+// starts reloading changed files, runs in `PreUpdate`.
+pub fn update_assets(world: &mut World) {
+    let finished = match world.get_resource::<AssetServer>() {
+        Some(server) => {
+            server.reload_changed();
+            server.finished()
+        }
+        None => return,
+    };
+
+    for loaded in finished {
+        let error = match loaded.result {
+            Ok(insert) => {
+                insert(world);
+                None
+            }
+            Err(err) => Some(err),
+        };
+
+        world
+            .resource::<AssetServer>()
+            .finish(loaded.id, &loaded.path, error);
+    }
+}
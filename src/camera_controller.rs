@@ -0,0 +1,114 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+use crate::{
+    input::{CursorPosition, Input, MouseButton, MouseMotion},
+    plugin::Plugin,
+    rendering::{camera::Camera, renderer::Renderer},
+    schedule::Update,
+    AppBuilder,
+};
+
+// Keeps the camera from flipping over the poles.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+// Orbits the camera under the cursor around its target while `button` is held
+// and zooms it with the scroll wheel. Assumes the camera's up vector is +Y.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct OrbitController {
+    pub button: MouseButton,
+    // Radians per pixel of cursor movement.
+    pub sensitivity: f32,
+    // Fraction of the distance to the target per scrolled pixel.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            button: MouseButton::Right,
+            sensitivity: 0.01,
+            zoom_speed: 0.005,
+            min_distance: 0.5,
+            max_distance: 50.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct OrbitCameraPlugin {
+    pub controller: OrbitController,
+}
+
+impl Plugin for OrbitCameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(self.controller)
+            .add_systems(Update, orbit_camera);
+    }
+}
+
+pub fn orbit_camera(
+    controller: Res<OrbitController>,
+    mouse: Res<Input<MouseButton>>,
+    motion: Res<MouseMotion>,
+    cursor: Res<CursorPosition>,
+    renderer: Option<NonSend<Renderer>>,
+    mut cameras: Query<(Entity, &mut Camera)>,
+    mut dragged: Local<Option<Entity>>,
+) {
+    let hovered = match (cursor.0, renderer) {
+        (Some((window, position)), Some(renderer)) => renderer
+            .view_under_cursor(window, position)
+            .map(|(entity, _, _)| entity),
+        // Without a renderer there are no viewports to tell cameras apart.
+        _ => cameras.iter().next().map(|(entity, ..)| entity),
+    };
+
+    // Keep orbiting the camera the drag started on, even once the cursor
+    // leaves its viewport.
+    if mouse.just_pressed(controller.button) {
+        *dragged = hovered;
+    } else if !mouse.pressed(controller.button) {
+        *dragged = None;
+    }
+
+    let entity = match dragged.or(hovered) {
+        Some(entity) => entity,
+        None => return,
+    };
+
+    let mut camera = match cameras.get_mut(entity) {
+        Ok((_, camera)) => camera,
+        Err(_) => return,
+    };
+
+    let rotate = dragged.is_some() && motion.delta != Vec2::ZERO;
+    if !rotate && motion.scroll.y == 0.0 {
+        return;
+    }
+
+    let offset = camera.eye - camera.target;
+    let mut distance = offset.length();
+    let mut yaw = offset.x.atan2(offset.z);
+    let mut pitch = (offset.y / distance).clamp(-1.0, 1.0).asin();
+
+    if rotate {
+        yaw -= motion.delta.x * controller.sensitivity;
+        pitch = (pitch + motion.delta.y * controller.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    distance *= 1.0 - motion.scroll.y * controller.zoom_speed;
+    distance = distance.clamp(controller.min_distance, controller.max_distance);
+
+    let direction = Vec3::new(
+        yaw.sin() * pitch.cos(),
+        pitch.sin(),
+        yaw.cos() * pitch.cos(),
+    );
+
+    camera.eye = camera.target + direction * distance;
+}
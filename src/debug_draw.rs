@@ -0,0 +1,255 @@
+use std::f32::consts::TAU;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+use crate::rendering::bounds::Aabb;
+
+const CIRCLE_SEGMENTS: u32 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugLine {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub color: Vec3,
+    // Seconds left to live, lines with no duration are drawn for a single frame.
+    pub duration: f32,
+    pub depth_test: bool,
+}
+
+// Immediate mode debug drawing. Shapes are cleared after being drawn unless
+// given a duration.
+#[derive(Resource, Default)]
+pub struct DebugDraw {
+    lines: Vec<DebugLine>,
+}
+
+// Lines added by a single shape call, used to tweak their options.
+pub struct DebugShape<'a> {
+    lines: &'a mut [DebugLine],
+}
+
+impl DebugShape<'_> {
+    pub fn duration(self, seconds: f32) -> Self {
+        self.lines
+            .iter_mut()
+            .for_each(|line| line.duration = seconds);
+        self
+    }
+
+    pub fn depth_test(self, enabled: bool) -> Self {
+        self.lines
+            .iter_mut()
+            .for_each(|line| line.depth_test = enabled);
+        self
+    }
+}
+
+impl DebugDraw {
+    pub fn lines(&self) -> &[DebugLine] {
+        &self.lines
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    // Ages lines by `delta` seconds and drops the expired ones. Called after drawing.
+    pub fn tick(&mut self, delta: f32) {
+        self.lines.retain_mut(|line| {
+            line.duration -= delta;
+            line.duration > 0.0
+        });
+    }
+
+    fn shape(
+        &mut self,
+        segments: impl IntoIterator<Item = (Vec3, Vec3)>,
+        color: Vec3,
+    ) -> DebugShape<'_> {
+        let start = self.lines.len();
+
+        self.lines
+            .extend(segments.into_iter().map(|(start, end)| DebugLine {
+                start,
+                end,
+                color,
+                duration: 0.0,
+                depth_test: true,
+            }));
+
+        DebugShape {
+            lines: &mut self.lines[start..],
+        }
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec3) -> DebugShape<'_> {
+        self.shape([(start, end)], color)
+    }
+
+    pub fn ray(&mut self, origin: Vec3, direction: Vec3, color: Vec3) -> DebugShape<'_> {
+        self.line(origin, origin + direction, color)
+    }
+
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Vec3) -> DebugShape<'_> {
+        let direction = end - start;
+        let length = direction.length();
+
+        if length <= f32::EPSILON {
+            return self.shape([], color);
+        }
+
+        let forward = direction / length;
+        let (right, up) = forward.any_orthonormal_pair();
+
+        let head = length * 0.2;
+        let base = end - forward * head;
+
+        let segments = [
+            (start, end),
+            (end, base + right * head * 0.5),
+            (end, base - right * head * 0.5),
+            (end, base + up * head * 0.5),
+            (end, base - up * head * 0.5),
+        ];
+
+        self.shape(segments, color)
+    }
+
+    // Box spanning -1 to 1 on every axis, transformed by `transform`.
+    pub fn cuboid(&mut self, transform: &Mat4, color: Vec3) -> DebugShape<'_> {
+        let corners = NDC_CUBE.map(|corner| transform.transform_point3(corner));
+        self.shape(box_segments(&corners), color)
+    }
+
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec3) -> DebugShape<'_> {
+        let transform = Mat4::from_scale_rotation_translation(
+            aabb.half_extents(),
+            Quat::IDENTITY,
+            aabb.center(),
+        );
+
+        self.cuboid(&transform, color)
+    }
+
+    // Draws the view volume of a camera or light from its view projection matrix.
+    pub fn frustum(&mut self, view_proj: &Mat4, color: Vec3) -> DebugShape<'_> {
+        let inverse = view_proj.inverse();
+
+        // Depth runs from 0 to 1 instead of -1 to 1.
+        let corners = NDC_CUBE.map(|corner| {
+            inverse.project_point3(corner * Vec3::new(1.0, 1.0, 0.5) + Vec3::new(0.0, 0.0, 0.5))
+        });
+
+        self.shape(box_segments(&corners), color)
+    }
+
+    pub fn circle(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        color: Vec3,
+    ) -> DebugShape<'_> {
+        let (u, v) = normal.normalize().any_orthonormal_pair();
+
+        let point = |i: u32| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+
+        self.shape(
+            (0..CIRCLE_SEGMENTS).map(|i| (point(i), point(i + 1))),
+            color,
+        )
+    }
+
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) -> DebugShape<'_> {
+        let start = self.lines.len();
+
+        self.circle(center, Vec3::X, radius, color);
+        self.circle(center, Vec3::Y, radius, color);
+        self.circle(center, Vec3::Z, radius, color);
+
+        DebugShape {
+            lines: &mut self.lines[start..],
+        }
+    }
+
+    // Square grid of `cells` by `cells` cells centered on `center`, lying in the
+    // plane perpendicular to `normal`.
+    pub fn grid(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        cells: u32,
+        spacing: f32,
+        color: Vec3,
+    ) -> DebugShape<'_> {
+        let (u, v) = normal.normalize().any_orthonormal_pair();
+        let half = cells as f32 * spacing * 0.5;
+
+        let segments = (0..=cells).flat_map(|i| {
+            let offset = i as f32 * spacing - half;
+
+            [
+                (
+                    center + u * offset - v * half,
+                    center + u * offset + v * half,
+                ),
+                (
+                    center + v * offset - u * half,
+                    center + v * offset + u * half,
+                ),
+            ]
+        });
+
+        self.shape(segments.collect::<Vec<_>>(), color)
+    }
+
+    // X, Y and Z axes of `transform` in red, green and blue.
+    pub fn axes(&mut self, transform: &Mat4, length: f32) -> DebugShape<'_> {
+        let start = self.lines.len();
+        let origin = transform.transform_point3(Vec3::ZERO);
+
+        for (axis, color) in [(Vec3::X, Vec3::X), (Vec3::Y, Vec3::Y), (Vec3::Z, Vec3::Z)] {
+            let end = transform.transform_point3(axis * length);
+            self.arrow(origin, end, color);
+        }
+
+        DebugShape {
+            lines: &mut self.lines[start..],
+        }
+    }
+}
+
+const NDC_CUBE: [Vec3; 8] = [
+    Vec3::new(-1.0, -1.0, -1.0),
+    Vec3::new(1.0, -1.0, -1.0),
+    Vec3::new(1.0, 1.0, -1.0),
+    Vec3::new(-1.0, 1.0, -1.0),
+    Vec3::new(-1.0, -1.0, 1.0),
+    Vec3::new(1.0, -1.0, 1.0),
+    Vec3::new(1.0, 1.0, 1.0),
+    Vec3::new(-1.0, 1.0, 1.0),
+];
+
+// Twelve edges of a box given its corners in `NDC_CUBE` order.
+fn box_segments(corners: &[Vec3; 8]) -> [(Vec3, Vec3); 12] {
+    let edge = |a: usize, b: usize| (corners[a], corners[b]);
+
+    [
+        edge(0, 1),
+        edge(1, 2),
+        edge(2, 3),
+        edge(3, 0),
+        edge(4, 5),
+        edge(5, 6),
+        edge(6, 7),
+        edge(7, 4),
+        edge(0, 4),
+        edge(1, 5),
+        edge(2, 6),
+        edge(3, 7),
+    ]
+}
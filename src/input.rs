@@ -0,0 +1,178 @@
+use std::{collections::HashSet, hash::Hash};
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+use winit::{
+    event::{ElementState, KeyboardInput, MouseScrollDelta, WindowEvent},
+    window::WindowId,
+};
+
+pub use winit::event::{MouseButton, VirtualKeyCode};
+
+use crate::{plugin::Plugin, schedule::PreUpdate, AppBuilder};
+
+// Pixels scrolled per line, for devices that report scrolling in lines.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+// Window events forwarded by `App::run`. Events that borrow the window, like
+// scale factor changes, are handled by the app and never forwarded.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct WindowInput {
+    pub window: WindowId,
+    pub event: WindowEvent<'static>,
+}
+
+// Pressed state of keys or buttons, with edges for the current frame.
+#[derive(Debug, Clone)]
+pub struct Input<T> {
+    pressed: HashSet<T>,
+    just_pressed: HashSet<T>,
+    just_released: HashSet<T>,
+}
+
+impl<T: Send + Sync + 'static> Resource for Input<T> {}
+
+impl<T> Default for Input<T> {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> Input<T> {
+    pub fn press(&mut self, input: T) {
+        if self.pressed.insert(input) {
+            self.just_pressed.insert(input);
+        }
+    }
+
+    pub fn release(&mut self, input: T) {
+        if self.pressed.remove(&input) {
+            self.just_released.insert(input);
+        }
+    }
+
+    pub fn pressed(&self, input: T) -> bool {
+        self.pressed.contains(&input)
+    }
+
+    pub fn just_pressed(&self, input: T) -> bool {
+        self.just_pressed.contains(&input)
+    }
+
+    pub fn just_released(&self, input: T) -> bool {
+        self.just_released.contains(&input)
+    }
+
+    pub fn get_pressed(&self) -> impl Iterator<Item = &T> {
+        self.pressed.iter()
+    }
+
+    // Forgets this frame's edges, keeping the pressed state.
+    pub fn clear(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    pub fn reset(&mut self) {
+        self.pressed.clear();
+        self.clear();
+    }
+}
+
+// Window under the cursor and the cursor position in it, in physical pixels
+// with the origin at the top left corner.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct CursorPosition(pub Option<(WindowId, Vec2)>);
+
+// Cursor movement and scrolling accumulated over the current frame, in pixels.
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct MouseMotion {
+    pub delta: Vec2,
+    pub scroll: Vec2,
+}
+
+// Systems updating the input resources in `PreUpdate`, order other `PreUpdate`
+// systems reading input after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InputSet;
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<WindowInput>()
+            .init_resource::<Input<VirtualKeyCode>>()
+            .init_resource::<Input<MouseButton>>()
+            .init_resource::<CursorPosition>()
+            .init_resource::<MouseMotion>()
+            .add_systems(PreUpdate, update_input.in_set(InputSet));
+    }
+}
+
+pub fn update_input(
+    mut events: EventReader<WindowInput>,
+    mut keyboard: ResMut<Input<VirtualKeyCode>>,
+    mut mouse: ResMut<Input<MouseButton>>,
+    mut cursor: ResMut<CursorPosition>,
+    mut motion: ResMut<MouseMotion>,
+) {
+    keyboard.clear();
+    mouse.clear();
+    *motion = MouseMotion::default();
+
+    for WindowInput { window, event } in events.read() {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => match state {
+                ElementState::Pressed => keyboard.press(*key),
+                ElementState::Released => keyboard.release(*key),
+            },
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => mouse.press(*button),
+                ElementState::Released => mouse.release(*button),
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+
+                // Moving to another window isn't motion.
+                if let Some((previous_window, previous)) = cursor.0 {
+                    if previous_window == *window {
+                        motion.delta += position - previous;
+                    }
+                }
+
+                cursor.0 = Some((*window, position));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if matches!(cursor.0, Some((cursor_window, _)) if cursor_window == *window) {
+                    cursor.0 = None;
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                motion.scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y) * PIXELS_PER_LINE,
+                    MouseScrollDelta::PixelDelta(delta) => {
+                        Vec2::new(delta.x as f32, delta.y as f32)
+                    }
+                };
+            }
+            // Keys released while unfocused would otherwise stay pressed.
+            WindowEvent::Focused(false) => {
+                keyboard.reset();
+                mouse.reset();
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,73 +1,155 @@
+pub mod animation;
+pub mod asset;
+pub mod camera_controller;
+pub mod debug_draw;
+pub mod input;
 pub mod loader;
+pub mod picking;
+pub mod plugin;
 pub mod rendering;
+pub mod scene;
+pub mod schedule;
+pub mod time;
+pub mod transform;
+pub mod watcher;
+pub mod window;
 
-use loader::load;
-use rendering::{camera::Camera, renderer::Renderer};
+pub use plugin::{DefaultPlugins, Plugin};
 
-use bevy_ecs::{schedule::Schedule, world::World};
-use bevy_math::prelude::*;
+use input::WindowInput;
+use rendering::{plugin::render_frame, renderer::Renderer};
+use schedule::{FixedTimestep, FixedUpdate, PostUpdate, PreUpdate, RenderExtract, Startup, Update};
+use time::Time;
+use window::WindowSettings;
+
+use bevy_ecs::{
+    event::{event_update_system, Events},
+    schedule::{IntoSystemConfigs, IntoSystemSetConfigs, Schedule, ScheduleLabel, Schedules},
+    system::Resource,
+    world::{FromWorld, World},
+};
+
+use std::{any::TypeId, collections::HashSet, time::Instant};
 
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::WindowId,
 };
 
 pub struct App {
     world: World,
-    renderer: Renderer,
-    event_loop: EventLoop<()>,
+    event_loop: Option<EventLoop<()>>,
+
+    started: bool,
+}
+
+// Collects plugins, resources and systems before the app is run.
+pub struct AppBuilder {
+    world: World,
+    event_loop: Option<EventLoop<()>>,
 
-    update_schedule: Schedule,
+    plugins: HashSet<TypeId>,
 }
 
 impl App {
-    pub fn new() -> Self {
-        env_logger::init();
+    pub fn builder() -> AppBuilder {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.init_resource::<FixedTimestep>();
+
+        let mut schedules = Schedules::new();
+        schedules.insert(Schedule::new(Startup));
+        schedules.insert(Schedule::new(PreUpdate));
+        schedules.insert(Schedule::new(FixedUpdate));
+        schedules.insert(Schedule::new(Update));
+        schedules.insert(Schedule::new(PostUpdate));
+        schedules.insert(Schedule::new(RenderExtract));
+        world.insert_resource(schedules);
+
+        AppBuilder {
+            world,
+            event_loop: None,
+            plugins: HashSet::new(),
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
 
-        let event_loop = EventLoop::new();
-        let window = WindowBuilder::new().build(&event_loop).unwrap();
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
 
-        let (vertices, indices) = load("assets/cube.gltf").expect("Failed to load cube!");
+    // Runs every schedule once in order, except `Startup` which only runs on the
+    // first update, and `FixedUpdate` which runs as many times as the elapsed
+    // time allows.
+    pub fn update(&mut self) {
+        self.world.resource_mut::<Time>().update();
 
-        let mut renderer = pollster::block_on(Renderer::new(window));
-        renderer.create_mesh(vertices, indices, &Mat4::IDENTITY);
+        if !self.started {
+            self.world.run_schedule(Startup);
+            self.started = true;
+        }
 
-        let camera = Camera {
-            eye: Vec3::new(1.0, 1.0, 1.0),
-            target: Vec3::ZERO,
-            up: Vec3::Y,
-            fovy: 75.0,
-            znear: 0.1,
-            zfar: 100.0,
-        };
+        self.world.run_schedule(PreUpdate);
 
-        let mut world = World::new();
-        world.insert_resource(camera);
+        let delta = self.world.resource::<Time>().delta();
+        let mut fixed = self.world.resource_mut::<FixedTimestep>();
+        let steps = fixed.accumulate(delta);
+        let (timestep, accumulator) = (fixed.timestep, fixed.accumulator());
 
-        let update_schedule = Schedule::default();
+        self.world
+            .resource_mut::<Time>()
+            .set_fixed(timestep, accumulator);
 
-        Self {
-            world,
-            renderer,
-            event_loop,
-            update_schedule,
+        for _ in 0..steps {
+            self.world.run_schedule(FixedUpdate);
+        }
+
+        self.world.run_schedule(Update);
+        self.world.run_schedule(PostUpdate);
+        self.world.run_schedule(RenderExtract);
+    }
+
+    fn primary_window(&self) -> Option<WindowId> {
+        self.world
+            .get_non_send_resource::<Renderer>()
+            .map(|renderer| renderer.primary_window())
+    }
+
+    fn has_window(&self, window_id: WindowId) -> bool {
+        self.world
+            .get_non_send_resource::<Renderer>()
+            .is_some_and(|renderer| renderer.window(window_id).is_some())
+    }
+
+    fn resize_surface(&mut self, window_id: WindowId, size: winit::dpi::PhysicalSize<u32>) {
+        if let Some(mut renderer) = self.world.get_non_send_resource_mut::<Renderer>() {
+            renderer.resize_surface(window_id, size);
+        }
+    }
+
+    // Closing the primary window exits, other windows are simply dropped.
+    fn close_window(&mut self, window_id: WindowId, control_flow: &mut ControlFlow) {
+        if Some(window_id) == self.primary_window() {
+            *control_flow = ControlFlow::Exit;
+        } else if let Some(mut renderer) = self.world.get_non_send_resource_mut::<Renderer>() {
+            renderer.remove_window(window_id);
         }
     }
 
     pub fn run(mut self) {
-        self.event_loop.run(move |event, _, control_flow| {
+        let event_loop = self.event_loop.take().unwrap_or_default();
+        let mut next_frame = Instant::now();
+
+        event_loop.run(move |event, _, control_flow| {
             match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == self.renderer.get_window().id() => {
-                    // handle input
-                    //if input(event) { return; }
-
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
+                Event::WindowEvent { event, window_id } if self.has_window(window_id) => {
+                    match &event {
+                        WindowEvent::CloseRequested => self.close_window(window_id, control_flow),
+                        WindowEvent::KeyboardInput {
                             input:
                                 KeyboardInput {
                                     state: ElementState::Pressed,
@@ -77,40 +159,169 @@ impl App {
                             ..
                         } => *control_flow = ControlFlow::Exit,
                         WindowEvent::Resized(physical_size) => {
-                            self.renderer.resize_surface(*physical_size);
+                            self.resize_surface(window_id, *physical_size);
                         }
                         WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                             // new_inner_size is &&mut so w have to dereference it twice
-                            self.renderer.resize_surface(**new_inner_size);
+                            self.resize_surface(window_id, **new_inner_size);
                         }
                         _ => {}
                     }
+
+                    if !self.world.contains_resource::<Events<WindowInput>>() {
+                        return;
+                    }
+
+                    if let Some(event) = event.to_static() {
+                        self.world.send_event(WindowInput {
+                            window: window_id,
+                            event,
+                        });
+                    }
                 }
-                Event::RedrawRequested(window_id)
-                    if window_id == self.renderer.get_window().id() =>
-                {
-                    self.update_schedule.run(&mut self.world);
-                    let camera = self.world.get_resource::<Camera>();
+                // Every window is drawn when the primary one is redrawn.
+                Event::RedrawRequested(window_id) if Some(window_id) == self.primary_window() => {
+                    let frame_start = Instant::now();
 
-                    match self.renderer.render(camera) {
+                    self.update();
+
+                    let frame_time = self
+                        .world
+                        .get_resource::<WindowSettings>()
+                        .and_then(|settings| settings.frame_time());
+
+                    // Catch up when more than a frame behind instead of bursting.
+                    next_frame = match frame_time {
+                        Some(frame_time) => (next_frame + frame_time).max(frame_start),
+                        None => frame_start,
+                    };
+
+                    match render_frame(&mut self.world) {
                         Ok(_) => {}
-                        // Reconfigure the surface if it's lost or outdated
-                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                            self.renderer.resize_surface(self.renderer.get_size())
-                        }
                         // The system is out of memory, we should probably quit
                         Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-
-                        Err(wgpu::SurfaceError::Timeout) => log::warn!("Surface timeout"),
+                        Err(err) => log::warn!("Surface error: {}", err),
                     }
                 }
                 Event::RedrawEventsCleared => {
+                    // Sleep until the next frame when the frame rate is limited.
+                    if Instant::now() < next_frame {
+                        *control_flow = ControlFlow::WaitUntil(next_frame);
+                        return;
+                    }
+
+                    *control_flow = ControlFlow::Poll;
+
                     // RedrawRequested will only trigger once, unless we manually
                     // request it.
-                    self.renderer.get_window().request_redraw();
+                    match self.world.get_non_send_resource::<Renderer>() {
+                        Some(renderer) => renderer.get_window().request_redraw(),
+                        // Without a window there is nothing to redraw, update directly.
+                        None => self.update(),
+                    }
                 }
                 _ => {}
             }
         });
     }
 }
+
+impl AppBuilder {
+    // Builds the plugin right away, so plugins may rely on the ones added before
+    // them. Adding the same plugin type twice is ignored.
+    pub fn add_plugin<P: Plugin>(&mut self, plugin: P) -> &mut Self {
+        if !self.plugins.insert(TypeId::of::<P>()) {
+            log::warn!("Plugin {} was already added", plugin.name());
+            return self;
+        }
+
+        plugin.build(self);
+        self
+    }
+
+    pub fn insert_resource<R: Resource>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_resource(resource);
+        self
+    }
+
+    // Inserts the default value unless the resource already exists.
+    pub fn init_resource<R: Resource + FromWorld>(&mut self) -> &mut Self {
+        self.world.init_resource::<R>();
+        self
+    }
+
+    pub fn insert_non_send_resource<R: 'static>(&mut self, resource: R) -> &mut Self {
+        self.world.insert_non_send_resource(resource);
+        self
+    }
+
+    // Registers an event type; its buffers are swapped every frame in `PreUpdate`
+    // so events live for two frames.
+    pub fn add_event<E: bevy_ecs::event::Event>(&mut self) -> &mut Self {
+        if !self.world.contains_resource::<Events<E>>() {
+            self.world.init_resource::<Events<E>>();
+            self.add_systems(PreUpdate, event_update_system::<E>);
+        }
+
+        self
+    }
+
+    // Adds systems to the schedule with the given label, creating it if needed.
+    // Ordering constraints only apply within a schedule.
+    pub fn add_systems<M>(
+        &mut self,
+        label: impl ScheduleLabel,
+        systems: impl IntoSystemConfigs<M>,
+    ) -> &mut Self {
+        self.schedule_mut(label).add_systems(systems);
+        self
+    }
+
+    pub fn configure_sets(
+        &mut self,
+        label: impl ScheduleLabel,
+        sets: impl IntoSystemSetConfigs,
+    ) -> &mut Self {
+        self.schedule_mut(label).configure_sets(sets);
+        self
+    }
+
+    // Adds a schedule that plugins run themselves with `World::run_schedule`,
+    // replacing any schedule with the same label.
+    pub fn add_schedule(&mut self, schedule: Schedule) -> &mut Self {
+        self.world.resource_mut::<Schedules>().insert(schedule);
+        self
+    }
+
+    pub fn schedule_mut(&mut self, label: impl ScheduleLabel) -> &mut Schedule {
+        let label = label.intern();
+        let schedules = self.world.resource_mut::<Schedules>().into_inner();
+
+        if !schedules.contains(label) {
+            schedules.insert(Schedule::new(label));
+        }
+
+        schedules.get_mut(label).unwrap()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    // Created on first use, so apps without a window never open a display connection.
+    pub fn event_loop(&mut self) -> &EventLoop<()> {
+        self.event_loop.get_or_insert_with(EventLoop::new)
+    }
+
+    pub fn build(&mut self) -> App {
+        App {
+            world: std::mem::take(&mut self.world),
+            event_loop: self.event_loop.take(),
+            started: false,
+        }
+    }
+}
@@ -1,47 +1,261 @@
+use std::path::Path;
+
+use bevy_math::{Mat4, Vec3};
 use gltf::Error;
 
-use crate::rendering::vertex::Vertex;
+use crate::{
+    asset::{Asset, AssetServer, Handle},
+    plugin::Plugin,
+    rendering::{
+        bounds::{Aabb, BoundingSphere},
+        vertex::{MorphDelta, SkinVertex, Vertex},
+    },
+    scene::Scene,
+    transform::{GlobalTransform, Transform},
+    AppBuilder,
+};
+
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    // One per vertex for skinned meshes, empty otherwise.
+    pub skin: Vec<SkinVertex>,
+    // One per vertex for every morph target, one target after the other.
+    pub morph_targets: Vec<MorphDelta>,
+
+    // Local space bounds
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl MeshData {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        let positions = || vertices.iter().map(|v| Vec3::from(v.position));
 
-pub fn load(path: &str) -> Result<(Vec<Vertex>, Vec<u32>), Error> {
-    let mut vertices: Vec<Vertex> = vec![];
-    let mut indices: Vec<u32> = vec![];
+        let aabb = Aabb::from_points(positions());
+        let sphere = BoundingSphere::from_points(&aabb, positions());
+
+        Self {
+            vertices,
+            indices,
+            skin: vec![],
+            morph_targets: vec![],
+            aabb,
+            sphere,
+        }
+    }
+
+    // Ignored unless there is one per vertex.
+    pub fn with_skin(mut self, skin: Vec<SkinVertex>) -> Self {
+        if skin.len() == self.vertices.len() {
+            self.skin = skin;
+        }
+
+        self
+    }
+
+    // Ignored unless there is one delta per vertex in every target.
+    pub fn with_morph_targets(mut self, morph_targets: Vec<MorphDelta>) -> Self {
+        if !self.vertices.is_empty() && morph_targets.len() % self.vertices.len() == 0 {
+            self.morph_targets = morph_targets;
+        }
+
+        self
+    }
+
+    pub fn morph_target_count(&self) -> usize {
+        match self.vertices.len() {
+            0 => 0,
+            count => self.morph_targets.len() / count,
+        }
+    }
+
+    // Whether the renderer has to pose the vertices every frame.
+    pub fn is_deformed(&self) -> bool {
+        !self.skin.is_empty() || !self.morph_targets.is_empty()
+    }
+}
+
+// Every mesh of the file merged into one.
+impl Asset for MeshData {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        Ok(load(path)?)
+    }
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<MeshData, Error> {
+    let mut primitives = Primitives::default();
 
     let (gltf, buffers, _) = gltf::import(path)?;
     for mesh in gltf.meshes() {
-        for primitive in mesh.primitives() {
+        primitives.read(mesh.primitives(), &buffers);
+    }
+
+    Ok(primitives.into_mesh())
+}
+
+// Vertices and indices of glTF primitives merged into one mesh.
+#[derive(Default)]
+pub(crate) struct Primitives {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub skin: Vec<SkinVertex>,
+    // Deltas of every vertex for each target.
+    pub morph_targets: Vec<Vec<MorphDelta>>,
+}
+
+impl Primitives {
+    // Appends the primitives. Primitives without positions are skipped, missing
+    // colors default to white, missing weights leave vertices unskinned and
+    // missing morph targets leave them in place.
+    pub fn read<'a>(
+        &mut self,
+        primitives: impl Iterator<Item = gltf::Primitive<'a>>,
+        buffers: &[gltf::buffer::Data],
+    ) {
+        for primitive in primitives {
             let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
 
-            let mut iter_position = reader.read_positions().unwrap();
-            let mut iter_color = reader.read_colors(0).unwrap().into_rgb_f32();
-            let mut iter_uv = reader.read_tex_coords(0).unwrap().into_f32();
+            let iter_position = match reader.read_positions() {
+                Some(iter) => iter,
+                None => continue,
+            };
+            let mut iter_color = reader.read_colors(0).map(|iter| iter.into_rgb_f32());
+            let mut iter_uv = reader.read_tex_coords(0).map(|iter| iter.into_f32());
+            let mut iter_normal = reader.read_normals();
+            let mut iter_joints = reader.read_joints(0).map(|iter| iter.into_u16());
+            let mut iter_weights = reader.read_weights(0).map(|iter| iter.into_f32());
 
-            loop {
-                let mut vertex = Vertex::default();
+            let base_vertex = self.vertices.len() as u32;
 
-                if let Some(position) = iter_position.next() {
-                    vertex.position = position;
-                } else {
-                    break;
-                }
+            for position in iter_position {
+                let mut vertex = Vertex {
+                    position,
+                    color: [1.0; 3],
+                    ..Default::default()
+                };
 
-                if let Some(color) = iter_color.next() {
+                if let Some(color) = iter_color.as_mut().and_then(|iter| iter.next()) {
                     vertex.color = color;
                 }
 
-                if let Some(uv) = iter_uv.next() {
+                if let Some(uv) = iter_uv.as_mut().and_then(|iter| iter.next()) {
                     vertex.tex_coords = uv;
                 }
 
-                vertices.push(vertex);
-            }
+                if let Some(normal) = iter_normal.as_mut().and_then(|iter| iter.next()) {
+                    vertex.normal = normal;
+                }
+
+                let mut skin = SkinVertex::default();
 
-            if let Some(iter) = reader.read_indices() {
-                for index in iter.into_u32() {
-                    indices.push(index);
+                if let (Some(joints), Some(weights)) = (
+                    iter_joints.as_mut().and_then(|iter| iter.next()),
+                    iter_weights.as_mut().and_then(|iter| iter.next()),
+                ) {
+                    skin = SkinVertex {
+                        joints: joints.map(u32::from),
+                        weights,
+                    };
                 }
+
+                self.vertices.push(vertex);
+                self.skin.push(skin);
+            }
+
+            self.read_morph_targets(&reader, base_vertex as usize);
+
+            match reader.read_indices() {
+                Some(iter) => self
+                    .indices
+                    .extend(iter.into_u32().map(|index| base_vertex + index)),
+                None => self.indices.extend(base_vertex..self.vertices.len() as u32),
             }
         }
     }
 
-    Ok((vertices, indices))
+    fn read_morph_targets<'a, 's, F>(
+        &mut self,
+        reader: &gltf::mesh::Reader<'a, 's, F>,
+        base_vertex: usize,
+    ) where
+        F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+    {
+        let vertex_count = self.vertices.len();
+
+        for (index, (positions, normals, tangents)) in reader.read_morph_targets().enumerate() {
+            // Targets first seen in this primitive don't move earlier vertices.
+            if index == self.morph_targets.len() {
+                self.morph_targets
+                    .push(vec![MorphDelta::default(); base_vertex]);
+            }
+
+            let deltas = &mut self.morph_targets[index];
+            deltas.resize(vertex_count, MorphDelta::default());
+
+            let deltas = &mut deltas[base_vertex..];
+            for (delta, position) in deltas.iter_mut().zip(positions.into_iter().flatten()) {
+                delta.position = position;
+            }
+            for (delta, normal) in deltas.iter_mut().zip(normals.into_iter().flatten()) {
+                delta.normal = normal;
+            }
+            for (delta, tangent) in deltas.iter_mut().zip(tangents.into_iter().flatten()) {
+                delta.tangent = tangent;
+            }
+        }
+
+        // Targets missing from this primitive don't move its vertices.
+        for deltas in &mut self.morph_targets {
+            deltas.resize(vertex_count, MorphDelta::default());
+        }
+    }
+
+    // The skin is dropped when no vertex has weights.
+    pub fn into_mesh(self) -> MeshData {
+        let skinned = self
+            .skin
+            .iter()
+            .any(|vertex| vertex.weights.iter().any(|weight| *weight > 0.0));
+
+        let morph_targets = self.morph_targets.concat();
+
+        let mesh = MeshData::new(self.vertices, self.indices).with_morph_targets(morph_targets);
+        match skinned {
+            true => mesh.with_skin(self.skin),
+            false => mesh,
+        }
+    }
+}
+
+// Loads a glTF file in the background and spawns its scene once loaded. The
+// path is relative to the asset directory. Must be added after `ScenePlugin`.
+pub struct GltfPlugin {
+    pub path: String,
+    pub transform: Mat4,
+}
+
+impl GltfPlugin {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            transform: Mat4::IDENTITY,
+        }
+    }
+}
+
+impl Plugin for GltfPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let world = app.world_mut();
+        let scene: Handle<Scene> = world
+            .get_resource::<AssetServer>()
+            .expect("GltfPlugin requires AssetPlugin")
+            .load(&self.path);
+
+        world.spawn((
+            scene,
+            Transform::from_matrix(self.transform),
+            GlobalTransform::default(),
+        ));
+    }
 }
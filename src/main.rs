@@ -1,6 +1,10 @@
-use bismuth::App;
+use bismuth::{camera_controller::OrbitCameraPlugin, loader::GltfPlugin, App, DefaultPlugins};
 
 fn main() {
-    let app = App::new();
-    app.run();
+    App::builder()
+        .add_plugin(DefaultPlugins)
+        .add_plugin(OrbitCameraPlugin::default())
+        .add_plugin(GltfPlugin::new("cube.gltf"))
+        .build()
+        .run();
 }
@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+use crate::{
+    input::{CursorPosition, Input, MouseButton},
+    loader::MeshData,
+    plugin::Plugin,
+    rendering::{
+        bounds::{Aabb, BoundingSphere},
+        camera::Camera,
+        renderer::{MeshId, Renderer},
+    },
+    schedule::Update,
+    AppBuilder,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    // The direction is not renormalized, so distances along the transformed ray
+    // match distances along the original one.
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        Self {
+            origin: matrix.transform_point3(self.origin),
+            direction: matrix.transform_vector3(self.direction),
+        }
+    }
+
+    // Distance to the first intersection, zero when the origin is inside the box.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let inverse = self.direction.recip();
+
+        let t0 = (aabb.min - self.origin) * inverse;
+        let t1 = (aabb.max - self.origin) * inverse;
+
+        let near = t0.min(t1).max_element();
+        let far = t0.max(t1).min_element();
+
+        if near > far || far < 0.0 {
+            return None;
+        }
+
+        Some(near.max(0.0))
+    }
+
+    pub fn intersect_sphere(&self, sphere: &BoundingSphere) -> Option<f32> {
+        let offset = self.origin - sphere.center;
+
+        let a = self.direction.length_squared();
+        let b = offset.dot(self.direction);
+        let c = offset.length_squared() - sphere.radius * sphere.radius;
+
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let root = discriminant.sqrt();
+        let near = (-b - root) / a;
+        let far = (-b + root) / a;
+
+        if far < 0.0 {
+            return None;
+        }
+
+        Some(near.max(0.0))
+    }
+
+    // Möller–Trumbore, returns the distance and barycentric weights of a, b and c.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<(f32, Vec3)> {
+        const EPSILON: f32 = 1e-7;
+
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+
+        // Parallel to the triangle plane; back faces are hit too.
+        if determinant.abs() < EPSILON {
+            return None;
+        }
+
+        let inverse = determinant.recip();
+
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some((distance, Vec3::new(1.0 - u - v, u, v)))
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct Pickable {
+    pub mesh: Arc<MeshData>,
+    pub transform: Mat4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub position: Vec3,
+    pub triangle: u32,
+    pub barycentrics: Vec3,
+}
+
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PickingMode {
+    // Ray cast against bounding volumes and triangles.
+    #[default]
+    Cpu,
+    // Read back the mesh id buffer under the cursor, then ray cast that mesh only.
+    Gpu,
+}
+
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct Selection(pub Option<PickHit>);
+
+// Picks under the cursor on left click.
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PickingMode>()
+            .init_resource::<Selection>()
+            .add_systems(Update, pick_on_click);
+    }
+}
+
+pub fn pick_on_click(world: &mut World) {
+    let clicked = world
+        .get_resource::<Input<MouseButton>>()
+        .is_some_and(|mouse| mouse.just_pressed(MouseButton::Left));
+
+    if clicked {
+        Renderer::scope(world, pick_cursor);
+    }
+}
+
+// Picks under the cursor through the camera whose viewport is under it, with the
+// current `PickingMode`, and stores the result in `Selection`.
+pub fn pick_cursor(world: &mut World, renderer: &mut Renderer) {
+    let (window, position) = match world.resource::<CursorPosition>().0 {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    let (entity, position, size) = match renderer.view_under_cursor(window, position) {
+        Some(view) => view,
+        None => return,
+    };
+
+    let camera = match world.get::<Camera>(entity) {
+        Some(camera) => camera.clone(),
+        None => return,
+    };
+
+    let ray = camera.screen_to_ray(position, size);
+
+    let hit = match *world.resource::<PickingMode>() {
+        PickingMode::Cpu => pick(world, &ray),
+        PickingMode::Gpu => match renderer.pick_mesh_id(&camera, position, size) {
+            Some(mesh_id) => pick_mesh(world, mesh_id, &ray),
+            None => None,
+        },
+    };
+
+    if let Some(hit) = &hit {
+        log::info!("Picked {:?} at distance {}", hit.entity, hit.distance);
+    }
+
+    world.resource_mut::<Selection>().0 = hit;
+}
+
+pub fn pick(world: &mut World, ray: &Ray) -> Option<PickHit> {
+    let mut query = world.query::<(Entity, &Pickable)>();
+
+    query
+        .iter(world)
+        .filter_map(|(entity, pickable)| pick_entity(entity, pickable, ray))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+// Resolves a mesh id from the GPU id buffer to its entity and refines the hit on the CPU.
+pub fn pick_mesh(world: &mut World, mesh_id: MeshId, ray: &Ray) -> Option<PickHit> {
+    let mut query = world.query::<(Entity, &MeshId, &Pickable)>();
+
+    query
+        .iter(world)
+        .find(|(_, id, _)| **id == mesh_id)
+        .and_then(|(entity, _, pickable)| pick_entity(entity, pickable, ray))
+}
+
+pub fn pick_entity(entity: Entity, pickable: &Pickable, ray: &Ray) -> Option<PickHit> {
+    let mesh = &pickable.mesh;
+
+    // Broad phase in world space.
+    ray.intersect_sphere(&mesh.sphere.transform(&pickable.transform))?;
+    ray.intersect_aabb(&mesh.aabb.transform(&pickable.transform))?;
+
+    let local_ray = ray.transform(&pickable.transform.inverse());
+    let position = |index: u32| Vec3::from(mesh.vertices[index as usize].position);
+
+    let mut closest: Option<(f32, u32, Vec3)> = None;
+
+    for (triangle, indices) in mesh.indices.chunks_exact(3).enumerate() {
+        let a = position(indices[0]);
+        let b = position(indices[1]);
+        let c = position(indices[2]);
+
+        if let Some((distance, barycentrics)) = local_ray.intersect_triangle(a, b, c) {
+            let is_closer = match closest {
+                Some((closest, _, _)) => distance < closest,
+                None => true,
+            };
+
+            if is_closer {
+                closest = Some((distance, triangle as u32, barycentrics));
+            }
+        }
+    }
+
+    closest.map(|(distance, triangle, barycentrics)| PickHit {
+        entity,
+        distance,
+        position: ray.at(distance),
+        triangle,
+        barycentrics,
+    })
+}
@@ -0,0 +1,41 @@
+use crate::{
+    animation::AnimationPlugin, asset::AssetPlugin, input::InputPlugin, picking::PickingPlugin,
+    rendering::camera::CameraPlugin, rendering::RenderPlugin, scene::ScenePlugin,
+    transform::TransformPlugin, AppBuilder,
+};
+
+// A feature that registers its resources, events and systems on the app.
+pub trait Plugin: 'static {
+    fn build(&self, app: &mut AppBuilder);
+
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+pub struct LogPlugin;
+
+impl Plugin for LogPlugin {
+    fn build(&self, _app: &mut AppBuilder) {
+        // The logger may already be set by the application.
+        let _ = env_logger::try_init();
+    }
+}
+
+// Logging, asset loading, transforms, scenes, animation, a window with the
+// renderer, input, a default camera and picking.
+pub struct DefaultPlugins;
+
+impl Plugin for DefaultPlugins {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(LogPlugin)
+            .add_plugin(AssetPlugin)
+            .add_plugin(TransformPlugin)
+            .add_plugin(ScenePlugin)
+            .add_plugin(AnimationPlugin)
+            .add_plugin(RenderPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(CameraPlugin::default())
+            .add_plugin(PickingPlugin);
+    }
+}
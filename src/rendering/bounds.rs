@@ -0,0 +1,108 @@
+use bevy_math::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self {
+            min: Vec3::ZERO,
+            max: Vec3::ZERO,
+        }
+    }
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut iter = points.into_iter();
+
+        let first = match iter.next() {
+            Some(point) => point,
+            None => return Self::default(),
+        };
+
+        iter.fold(Self::new(first, first), |aabb, point| Self {
+            min: aabb.min.min(point),
+            max: aabb.max.max(point),
+        })
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    // Transforms the box and returns the box enclosing the result (Arvo's method).
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        let center = matrix.transform_point3(self.center());
+        let extents = self.half_extents();
+
+        let abs = Mat3::from_cols(
+            matrix.x_axis.truncate().abs(),
+            matrix.y_axis.truncate().abs(),
+            matrix.z_axis.truncate().abs(),
+        );
+        let extents = abs * extents;
+
+        Self {
+            min: center - extents,
+            max: center + extents,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    // Sphere centered on the box, just large enough to contain every point.
+    pub fn from_points(aabb: &Aabb, points: impl IntoIterator<Item = Vec3>) -> Self {
+        let center = aabb.center();
+        let radius = points
+            .into_iter()
+            .map(|point| point.distance_squared(center))
+            .fold(0.0, f32::max)
+            .sqrt();
+
+        Self { center, radius }
+    }
+
+    pub fn transform(&self, matrix: &Mat4) -> Self {
+        let center = matrix.transform_point3(self.center);
+        let scale = matrix
+            .x_axis
+            .truncate()
+            .length()
+            .max(matrix.y_axis.truncate().length())
+            .max(matrix.z_axis.truncate().length());
+
+        Self {
+            center,
+            radius: self.radius * scale,
+        }
+    }
+}
@@ -2,24 +2,220 @@ use std::f32::consts::PI;
 
 use bevy_ecs::prelude::*;
 use bevy_math::prelude::*;
+use winit::window::WindowId;
 
-#[derive(Resource)]
+use crate::{
+    picking::Ray,
+    plugin::Plugin,
+    rendering::{frustum::Frustum, render_texture::RenderTextureId},
+    AppBuilder,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    // Vertical field of view in degrees.
+    Perspective {
+        fovy: f32,
+    },
+    // Vertical extent of the view volume; width follows the aspect ratio.
+    Orthographic {
+        height: f32,
+    },
+    // Explicit view volume bounds, aspect ratio is ignored.
+    OrthographicBounds {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+    },
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self::Perspective { fovy: 75.0 }
+    }
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
 pub struct Camera {
     pub eye: Vec3,
     pub target: Vec3,
     pub up: Vec3,
-    pub fovy: f32,
+    pub projection: Projection,
     pub znear: f32,
     pub zfar: f32,
+    // Cameras draw from the lowest order to the highest, so a camera drawing to a
+    // texture should come before the cameras looking at it.
+    pub order: isize,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            eye: Vec3::new(1.0, 1.0, 1.0),
+            target: Vec3::ZERO,
+            up: Vec3::Y,
+            projection: Projection::default(),
+            znear: 0.1,
+            zfar: 100.0,
+            order: 0,
+        }
+    }
+}
+
+// Window or texture a camera draws to, cameras without one draw to the primary
+// window.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RenderTarget {
+    #[default]
+    PrimaryWindow,
+    Window(WindowId),
+    Texture(RenderTextureId),
+}
+
+// Area of the render target a camera draws to, in fractions of the target size
+// with the origin at the top left corner. Cameras without one cover the target.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            min: Vec2::ZERO,
+            max: Vec2::ONE,
+        }
+    }
+}
+
+impl Viewport {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    // Offset and size in pixels on a target of `size` pixels, at least one pixel big.
+    pub fn to_pixels(&self, size: UVec2) -> (UVec2, UVec2) {
+        let target = size.as_vec2();
+        let min = (self.min.clamp(Vec2::ZERO, Vec2::ONE) * target).round();
+        let max = (self.max.clamp(Vec2::ZERO, Vec2::ONE) * target).round();
+
+        let offset = min.min((target - 1.0).max(Vec2::ZERO));
+        let extent = (max - offset).max(Vec2::ONE);
+
+        (offset.as_uvec2(), extent.as_uvec2())
+    }
+}
+
+// How a camera draws transparent meshes, cameras without one sort them.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Transparency {
+    // Blended back to front, mesh by mesh. Intersecting meshes blend in the
+    // wrong order where they overlap.
+    #[default]
+    Sorted,
+    // Weighted blended order-independent transparency, approximate but correct
+    // for intersecting meshes. Only materials whose state sets `oit` use it,
+    // the others are still sorted.
+    WeightedBlended,
+}
+
+// Spawns a camera drawing to the whole primary window.
+#[derive(Default)]
+pub struct CameraPlugin {
+    pub camera: Camera,
+}
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.world_mut().spawn(self.camera.clone());
+    }
 }
 
 impl Camera {
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        !matches!(self.projection, Projection::Perspective { .. })
+    }
+
+    pub fn get_view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn get_projection_matrix(&self, aspect: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective { fovy } => {
+                let fovy = fovy * (PI / 180.0);
+                Mat4::perspective_rh(fovy, aspect, self.znear, self.zfar)
+            }
+            Projection::Orthographic { height } => {
+                let half_height = height * 0.5;
+                let half_width = half_height * aspect;
+
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.znear,
+                    self.zfar,
+                )
+            }
+            Projection::OrthographicBounds {
+                left,
+                right,
+                bottom,
+                top,
+            } => Mat4::orthographic_rh(left, right, bottom, top, self.znear, self.zfar),
+        }
+    }
+
     pub fn get_view_projection_matrix(&self, aspect: f32) -> Mat4 {
-        let fovy = self.fovy * (PI / 180.0);
+        let view = self.get_view_matrix();
+        let proj = self.get_projection_matrix(aspect);
+
+        proj * view
+    }
+
+    pub fn get_inverse_view_matrix(&self) -> Mat4 {
+        self.get_view_matrix().inverse()
+    }
+
+    pub fn get_inverse_projection_matrix(&self, aspect: f32) -> Mat4 {
+        self.get_projection_matrix(aspect).inverse()
+    }
+
+    pub fn get_inverse_view_projection_matrix(&self, aspect: f32) -> Mat4 {
+        self.get_view_projection_matrix(aspect).inverse()
+    }
+
+    pub fn get_frustum(&self, aspect: f32) -> Frustum {
+        Frustum::from_view_projection(&self.get_view_projection_matrix(aspect))
+    }
+
+    // Cursor position in physical pixels, origin at the top left corner.
+    pub fn screen_to_ray(&self, position: Vec2, size: Vec2) -> Ray {
+        let ndc = Vec2::new(
+            position.x / size.x * 2.0 - 1.0,
+            1.0 - position.y / size.y * 2.0,
+        );
+
+        let inverse = self.get_inverse_view_projection_matrix(size.x / size.y);
+
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
 
-        let view = Mat4::look_at_rh(self.eye, self.target, self.up);
-        let proj = Mat4::perspective_rh(fovy, aspect, self.znear, self.zfar);
+        // Perspective rays start at the eye so hit distances are measured from the camera.
+        let origin = if self.is_orthographic() {
+            near
+        } else {
+            self.eye
+        };
 
-        return proj * view;
+        Ray::new(origin, far - near)
     }
 }
@@ -0,0 +1,245 @@
+use std::mem;
+
+use wgpu::util::DeviceExt;
+
+use crate::rendering::{frustum::Frustum, megabuffer::MegaBuffer};
+
+const WORKGROUP_SIZE: u32 = 64;
+
+const CULL_ENABLED: u32 = 1;
+const FIRST_INSTANCE: u32 = 2;
+
+pub const INDIRECT_STRIDE: wgpu::BufferAddress =
+    mem::size_of::<wgpu::util::DrawIndexedIndirect>() as wgpu::BufferAddress;
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniform {
+    planes: [[f32; 4]; 6],
+    draw_count: u32,
+    flags: u32,
+    _padding: [u32; 2],
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DrawData {
+    // Local space bounding sphere, radius in w.
+    pub sphere: [f32; 4],
+    pub index_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub instance: u32,
+}
+
+// Features required by the GPU driven path beyond plain compute support.
+pub fn optional_features() -> wgpu::Features {
+    wgpu::Features::MULTI_DRAW_INDIRECT | wgpu::Features::INDIRECT_FIRST_INSTANCE
+}
+
+pub fn is_supported(adapter: &wgpu::Adapter) -> bool {
+    let flags = adapter.get_downlevel_capabilities().flags;
+    flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+}
+
+// Culls every draw on the GPU and writes the surviving ones as indirect commands.
+pub struct GpuCulling {
+    draws: MegaBuffer<DrawData>,
+
+    uniform_buffer: wgpu::Buffer,
+    indirect_buffer: wgpu::Buffer,
+    indirect_capacity: usize,
+
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    pipeline: wgpu::ComputePipeline,
+
+    multi_draw: bool,
+    first_instance: bool,
+}
+
+impl GpuCulling {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let features = device.features();
+
+        let draws = MegaBuffer::new(device, "draw_buffer", wgpu::BufferUsages::STORAGE);
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("cull_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[CullUniform::default()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let indirect_capacity = 64;
+        let indirect_buffer = create_indirect_buffer(device, indirect_capacity);
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+            ],
+            label: Some("cull_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("cull_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/cull.wgsl"));
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("cull_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cull_draws",
+        });
+
+        let first_instance = features.contains(wgpu::Features::INDIRECT_FIRST_INSTANCE);
+        let multi_draw = first_instance && features.contains(wgpu::Features::MULTI_DRAW_INDIRECT);
+
+        Self {
+            draws,
+            uniform_buffer,
+            indirect_buffer,
+            indirect_capacity,
+            bind_group_layout,
+            bind_group: None,
+            pipeline,
+            multi_draw,
+            first_instance,
+        }
+    }
+
+    pub fn push_draw(&mut self, draw: DrawData) -> u32 {
+        self.draws.push(&[draw])
+    }
+
+    pub fn set_draw(&mut self, index: u32, draw: DrawData) {
+        self.draws.set(index as usize, draw);
+    }
+
+    pub fn draw_count(&self) -> u32 {
+        self.draws.len() as u32
+    }
+
+    pub fn indirect_buffer(&self) -> &wgpu::Buffer {
+        &self.indirect_buffer
+    }
+
+    pub fn supports_multi_draw(&self) -> bool {
+        self.multi_draw
+    }
+
+    pub fn supports_first_instance(&self) -> bool {
+        self.first_instance
+    }
+
+    // Uploads pending draws and the frustum. `instances_reallocated` must be set
+    // whenever the instance buffer was recreated since the last call.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &wgpu::Buffer,
+        instances_reallocated: bool,
+        frustum: Option<&Frustum>,
+    ) {
+        let mut rebind = instances_reallocated | self.draws.upload(device, queue);
+
+        if self.draws.len() > self.indirect_capacity {
+            self.indirect_capacity = self.draws.len().next_power_of_two();
+            self.indirect_buffer = create_indirect_buffer(device, self.indirect_capacity);
+            rebind = true;
+        }
+
+        if rebind || self.bind_group.is_none() {
+            self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: self.uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: self.draws.buffer().as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: instances.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: self.indirect_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("cull_bind_group"),
+            }));
+        }
+
+        let mut uniform = CullUniform {
+            draw_count: self.draw_count(),
+            ..Default::default()
+        };
+
+        if let Some(frustum) = frustum {
+            uniform.planes = frustum.planes.map(|plane| plane.to_array());
+            uniform.flags |= CULL_ENABLED;
+        }
+
+        if self.first_instance {
+            uniform.flags |= FIRST_INSTANCE;
+        }
+
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let bind_group = match &self.bind_group {
+            Some(bind_group) if !self.draws.is_empty() => bind_group,
+            _ => return,
+        };
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Cull Pass"),
+            timestamp_writes: None,
+        });
+
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.draw_count().div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+fn create_indirect_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("indirect_buffer"),
+        size: capacity as wgpu::BufferAddress * INDIRECT_STRIDE,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+        mapped_at_creation: false,
+    })
+}
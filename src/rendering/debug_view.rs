@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+use wgpu::util::DeviceExt;
+
+use crate::rendering::{
+    camera::Camera,
+    megabuffer::MegaBuffer,
+    pipeline::{create_pipeline, create_pipeline_layout, RenderState},
+    vertex::Vertex,
+};
+
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ViewMode {
+    #[default]
+    Shaded,
+    Wireframe,
+    Normals,
+    Uvs,
+    VertexColors,
+    Depth,
+    Overdraw,
+    MeshId,
+}
+
+impl ViewMode {
+    pub const ALL: [ViewMode; 8] = [
+        ViewMode::Shaded,
+        ViewMode::Wireframe,
+        ViewMode::Normals,
+        ViewMode::Uvs,
+        ViewMode::VertexColors,
+        ViewMode::Depth,
+        ViewMode::Overdraw,
+        ViewMode::MeshId,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|mode| *mode == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn fragment_entry_point(self) -> Option<&'static str> {
+        match self {
+            ViewMode::Shaded => None,
+            ViewMode::Wireframe => Some("fragment_wireframe"),
+            ViewMode::Normals => Some("fragment_normals"),
+            ViewMode::Uvs => Some("fragment_uvs"),
+            ViewMode::VertexColors => Some("fragment_vertex_colors"),
+            ViewMode::Depth => Some("fragment_depth"),
+            ViewMode::Overdraw => Some("fragment_overdraw"),
+            ViewMode::MeshId => Some("fragment_mesh_id"),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DebugViewUniform {
+    znear: f32,
+    zfar: f32,
+    orthographic: u32,
+    _padding: u32,
+}
+
+// De-indexed position with the barycentric coordinate of its triangle corner,
+// used to draw wireframes when line polygon mode is unavailable.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BarycentricVertex {
+    pub position: [f32; 3],
+    pub barycentric: [f32; 3],
+}
+
+impl BarycentricVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 4 => Float32x3];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+pub struct DebugViewRenderer {
+    uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+
+    pipelines: HashMap<ViewMode, wgpu::RenderPipeline>,
+
+    // Only used when the device lacks `POLYGON_MODE_LINE`.
+    barycentric_pipeline: Option<wgpu::RenderPipeline>,
+    barycentric_vertices: MegaBuffer<BarycentricVertex>,
+}
+
+impl DebugViewRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        vertex_layout: wgpu::VertexBufferLayout<'static>,
+        instance_layout: wgpu::VertexBufferLayout<'static>,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("debug_view_uniform_buffer"),
+            contents: bytemuck::cast_slice(&[DebugViewUniform::default()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("debug_view_bind_group_layout"),
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("debug_view_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/debug_view.wgsl"));
+
+        let pipeline_layout = create_pipeline_layout(
+            device,
+            Some("debug_view_pipeline_layout"),
+            &[uniform_bind_group_layout, &bind_group_layout],
+        );
+
+        let polygon_mode_line = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE);
+
+        let buffers = [vertex_layout, instance_layout.clone()];
+
+        let create = |vertex_entry_point, buffers: &[wgpu::VertexBufferLayout], mode: ViewMode| {
+            let mut state = RenderState::default();
+            let mut blend = wgpu::BlendState::REPLACE;
+
+            match mode {
+                ViewMode::Wireframe if polygon_mode_line => {
+                    state.polygon_mode = wgpu::PolygonMode::Line;
+                    state.cull_mode = None;
+                }
+                ViewMode::Wireframe => {
+                    state.cull_mode = None;
+                }
+                ViewMode::Overdraw => {
+                    state.depth_write = false;
+                    state.depth_compare = wgpu::CompareFunction::Always;
+                    blend = wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::REPLACE,
+                    };
+                }
+                _ => {}
+            }
+
+            let fragment_entry_point = match vertex_entry_point {
+                "vertex_barycentric" => "fragment_wireframe_barycentric",
+                _ => mode.fragment_entry_point().unwrap(),
+            };
+
+            create_pipeline(
+                device,
+                Some("debug_view_pipeline"),
+                wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vertex_entry_point,
+                    buffers,
+                },
+                wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fragment_entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                },
+                &pipeline_layout,
+                &state,
+            )
+        };
+
+        let pipelines = ViewMode::ALL
+            .into_iter()
+            .filter(|mode| *mode != ViewMode::Shaded)
+            .filter(|mode| *mode != ViewMode::Wireframe || polygon_mode_line)
+            .map(|mode| (mode, create("vertex", &buffers, mode)))
+            .collect();
+
+        let barycentric_pipeline = (!polygon_mode_line).then(|| {
+            create(
+                "vertex_barycentric",
+                &[BarycentricVertex::desc(), instance_layout.clone()],
+                ViewMode::Wireframe,
+            )
+        });
+
+        let barycentric_vertices =
+            MegaBuffer::new(device, "barycentric_buffer", wgpu::BufferUsages::VERTEX);
+
+        Self {
+            uniform_buffer,
+            uniform_bind_group,
+            pipelines,
+            barycentric_pipeline,
+            barycentric_vertices,
+        }
+    }
+
+    pub fn update_uniform_buffer(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let uniform = DebugViewUniform {
+            znear: camera.znear,
+            zfar: camera.zfar,
+            orthographic: camera.is_orthographic() as u32,
+            _padding: 0,
+        };
+
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // Pipeline drawing the regular indexed meshes, None for `ViewMode::Shaded`
+    // and for barycentric wireframes.
+    pub fn pipeline(&self, mode: ViewMode) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.get(&mode)
+    }
+
+    pub fn barycentric_pipeline(&self, mode: ViewMode) -> Option<&wgpu::RenderPipeline> {
+        match mode {
+            ViewMode::Wireframe => self.barycentric_pipeline.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn barycentric_buffer(&self) -> &wgpu::Buffer {
+        self.barycentric_vertices.buffer()
+    }
+
+    // Expands newly added meshes into one vertex per index, so vertex `i` of the
+    // barycentric buffer belongs to index `i` of the index buffer.
+    pub fn prepare_barycentric(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[Vertex],
+        indices: &[u32],
+        meshes: impl Iterator<Item = (u32, u32, i32)>,
+    ) {
+        const CORNERS: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+        let expanded = self.barycentric_vertices.len() as u32;
+
+        for (first_index, index_count, base_vertex) in meshes {
+            if first_index < expanded {
+                continue;
+            }
+
+            let range = first_index as usize..(first_index + index_count) as usize;
+            let expanded_mesh: Vec<_> = indices[range]
+                .iter()
+                .enumerate()
+                .map(|(i, index)| BarycentricVertex {
+                    position: vertices[(base_vertex + *index as i32) as usize].position,
+                    barycentric: CORNERS[i % 3],
+                })
+                .collect();
+
+            self.barycentric_vertices.push(&expanded_mesh);
+        }
+
+        self.barycentric_vertices.upload(device, queue);
+    }
+}
@@ -0,0 +1,62 @@
+use bevy_math::prelude::*;
+
+use crate::rendering::bounds::{Aabb, BoundingSphere};
+
+// Planes are stored as (normal, distance) with normals pointing inwards,
+// a point is inside when `normal.dot(point) + distance >= 0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    pub const LEFT: usize = 0;
+    pub const RIGHT: usize = 1;
+    pub const BOTTOM: usize = 2;
+    pub const TOP: usize = 3;
+    pub const NEAR: usize = 4;
+    pub const FAR: usize = 5;
+
+    // Extracts the planes from a view projection matrix with a [0, 1] depth range.
+    pub fn from_view_projection(view_proj: &Mat4) -> Self {
+        let row0 = view_proj.row(0);
+        let row1 = view_proj.row(1);
+        let row2 = view_proj.row(2);
+        let row3 = view_proj.row(3);
+
+        let planes = [
+            row3 + row0,
+            row3 - row0,
+            row3 + row1,
+            row3 - row1,
+            row2,
+            row3 - row2,
+        ]
+        .map(|plane| plane / plane.truncate().length());
+
+        Self { planes }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(point) + plane.w >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+
+            // Corner of the box furthest along the plane normal.
+            let positive = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+
+            normal.dot(positive) + plane.w >= 0.0
+        })
+    }
+}
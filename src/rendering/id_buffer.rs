@@ -0,0 +1,164 @@
+use std::sync::mpsc;
+
+use bevy_math::prelude::*;
+use wgpu::util::DeviceExt;
+
+use crate::rendering::texture;
+
+pub const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+// Single pixel render target holding the instance id under the cursor. The
+// scene is rendered with a pick matrix that zooms into that pixel, so only
+// meshes touching it have to be drawn.
+pub struct IdBuffer {
+    uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+
+    id_texture: wgpu::Texture,
+    pub id_view: wgpu::TextureView,
+    pub depth_view: wgpu::TextureView,
+
+    readback_buffer: wgpu::Buffer,
+
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl IdBuffer {
+    pub fn new(
+        device: &wgpu::Device,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        pipeline: wgpu::RenderPipeline,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("id_uniform_buffer"),
+            contents: bytemuck::cast_slice(&Mat4::IDENTITY.to_cols_array()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("id_uniform_bind_group"),
+        });
+
+        let size = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+
+        let id_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("id_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ID_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("id_depth_texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        let id_view = id_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("id_readback_buffer"),
+            size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            uniform_buffer,
+            uniform_bind_group,
+            id_texture,
+            id_view,
+            depth_view,
+            readback_buffer,
+            pipeline,
+        }
+    }
+
+    // Narrows the view projection to the pixel at `position` of a `size` sized viewport.
+    pub fn pick_matrix(view_proj: &Mat4, position: Vec2, size: Vec2) -> Mat4 {
+        let center = Vec2::new(
+            (position.x.floor() + 0.5) / size.x * 2.0 - 1.0,
+            1.0 - (position.y.floor() + 0.5) / size.y * 2.0,
+        );
+
+        let pick = Mat4::from_cols(
+            Vec4::new(size.x, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, size.y, 0.0, 0.0),
+            Vec4::Z,
+            Vec4::new(-center.x * size.x, -center.y * size.y, 0.0, 1.0),
+        );
+
+        pick * *view_proj
+    }
+
+    pub fn update_uniform_buffer(&self, queue: &wgpu::Queue, view_proj: &Mat4) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&view_proj.to_cols_array()),
+        );
+    }
+
+    pub fn copy_to_readback(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_texture_to_buffer(
+            self.id_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: None,
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    // Blocks until the copy submitted with `copy_to_readback` has finished.
+    pub fn read(&self, device: &wgpu::Device) -> Option<u32> {
+        let slice = self.readback_buffer.slice(..);
+
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            sender.send(result).ok();
+        });
+
+        device.poll(wgpu::Maintain::Wait);
+
+        let id = match receiver.recv() {
+            Ok(Ok(())) => {
+                let data = slice.get_mapped_range();
+                let id: u32 = bytemuck::pod_read_unaligned(&data);
+                Some(id)
+            }
+            _ => None,
+        };
+
+        self.readback_buffer.unmap();
+
+        id
+    }
+}
@@ -0,0 +1,23 @@
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    // Shines along the entity's -Z axis.
+    Directional,
+    Point,
+    // Cone along the entity's -Z axis, angles in radians from its center.
+    Spot { inner_angle: f32, outer_angle: f32 },
+}
+
+// Punctual light positioned by the entity's `GlobalTransform`. The built-in
+// shader doesn't light meshes yet, custom materials can read these.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    // Candela for point and spot lights, lux for directional ones.
+    pub intensity: f32,
+    // Distance at which the light stops, None for no limit.
+    pub range: Option<f32>,
+}
@@ -0,0 +1,144 @@
+use crate::{
+    debug_draw::DebugDraw,
+    rendering::{
+        megabuffer::MegaBuffer,
+        pipeline::{create_pipeline, create_pipeline_layout, RenderState},
+    },
+};
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl LineVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+// Batches debug lines into two line lists, one tested against the scene depth
+// and one drawn on top of everything.
+pub struct LineRenderer {
+    depth_tested: MegaBuffer<LineVertex>,
+    overlay: MegaBuffer<LineVertex>,
+
+    depth_tested_pipeline: wgpu::RenderPipeline,
+    overlay_pipeline: wgpu::RenderPipeline,
+}
+
+impl LineRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/line.wgsl"));
+
+        let pipeline_layout = create_pipeline_layout(
+            device,
+            Some("line_pipeline_layout"),
+            &[uniform_bind_group_layout],
+        );
+
+        let create = |label, depth_compare| {
+            create_pipeline(
+                device,
+                Some(label),
+                wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vertex",
+                    buffers: &[LineVertex::desc()],
+                },
+                wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fragment",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                },
+                &pipeline_layout,
+                &RenderState {
+                    topology: wgpu::PrimitiveTopology::LineList,
+                    cull_mode: None,
+                    depth_write: false,
+                    depth_compare,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let depth_tested_pipeline = create("line_pipeline", wgpu::CompareFunction::LessEqual);
+        let overlay_pipeline = create("line_overlay_pipeline", wgpu::CompareFunction::Always);
+
+        Self {
+            depth_tested: MegaBuffer::new(device, "line_buffer", wgpu::BufferUsages::VERTEX),
+            overlay: MegaBuffer::new(device, "line_overlay_buffer", wgpu::BufferUsages::VERTEX),
+            depth_tested_pipeline,
+            overlay_pipeline,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.depth_tested.is_empty() && self.overlay.is_empty()
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, debug_draw: &DebugDraw) {
+        self.depth_tested.clear();
+        self.overlay.clear();
+
+        for line in debug_draw.lines() {
+            let color = line.color.to_array();
+            let vertices = [
+                LineVertex {
+                    position: line.start.to_array(),
+                    color,
+                },
+                LineVertex {
+                    position: line.end.to_array(),
+                    color,
+                },
+            ];
+
+            if line.depth_test {
+                self.depth_tested.push(&vertices);
+            } else {
+                self.overlay.push(&vertices);
+            }
+        }
+
+        self.depth_tested.upload(device, queue);
+        self.overlay.upload(device, queue);
+    }
+
+    // Expects the camera uniforms to be bound at group 0.
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let batches = [
+            (&self.depth_tested, &self.depth_tested_pipeline),
+            (&self.overlay, &self.overlay_pipeline),
+        ];
+
+        for (vertices, pipeline) in batches {
+            if vertices.is_empty() {
+                continue;
+            }
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_vertex_buffer(0, vertices.buffer().slice(..));
+            render_pass.draw(0..vertices.len() as u32, 0..1);
+        }
+    }
+}
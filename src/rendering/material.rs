@@ -0,0 +1,776 @@
+use std::{
+    borrow::Cow,
+    collections::{
+        hash_map::{DefaultHasher, Entry},
+        HashMap,
+    },
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use bevy_ecs::prelude::*;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    asset::{Handle, HandleId},
+    rendering::{
+        oit,
+        pipeline::{create_pipeline, create_pipeline_layout, validated, RenderState},
+        preprocessor::ShaderDefs,
+        render_texture::{RenderTexture, RenderTextureId},
+        shader::ShaderSource,
+        texture,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialId(pub u32);
+
+// Makes a mesh draw with a material added by `Renderer::add_material`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshMaterial(pub MaterialId);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    #[default]
+    Opaque,
+    // Blends by the output alpha, which isn't premultiplied.
+    Alpha,
+    // Adds the color scaled by its alpha.
+    Additive,
+}
+
+impl BlendMode {
+    fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Opaque => wgpu::BlendState::REPLACE,
+            BlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            BlendMode::Additive => wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            },
+        }
+    }
+}
+
+// Meshes are drawn queue by queue in this order, transparent ones back to
+// front so they blend over everything behind them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RenderQueue {
+    #[default]
+    Opaque,
+    // Drawn after opaque meshes, which then occlude them before their
+    // fragments are discarded.
+    AlphaTest,
+    Transparent,
+}
+
+// Fixed function state of a material. The default matches the built-in one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialState {
+    pub blend: BlendMode,
+    // Set when the shader discards fragments below an alpha cutoff.
+    pub alpha_test: bool,
+    // Set when the shader has a `fragment_oit` entry point, only used by
+    // transparent materials.
+    pub oit: bool,
+    pub cull_mode: Option<wgpu::Face>,
+    pub depth_write: bool,
+    pub depth_compare: wgpu::CompareFunction,
+}
+
+impl Default for MaterialState {
+    fn default() -> Self {
+        Self {
+            blend: BlendMode::Opaque,
+            alpha_test: false,
+            oit: false,
+            cull_mode: Some(wgpu::Face::Back),
+            depth_write: true,
+            depth_compare: wgpu::CompareFunction::Less,
+        }
+    }
+}
+
+impl MaterialState {
+    pub fn queue(&self) -> RenderQueue {
+        match (self.blend, self.alpha_test) {
+            (BlendMode::Opaque, false) => RenderQueue::Opaque,
+            (BlendMode::Opaque, true) => RenderQueue::AlphaTest,
+            _ => RenderQueue::Transparent,
+        }
+    }
+
+    fn render_state(&self) -> RenderState {
+        RenderState {
+            cull_mode: self.cull_mode,
+            depth_write: self.depth_write,
+            depth_compare: self.depth_compare,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TextureSource {
+    // The texture of the built-in material.
+    Default,
+    // Meshes with the material aren't drawn into this same texture.
+    RenderTexture(RenderTextureId),
+    Image(Arc<image::DynamicImage>),
+    // Image loaded by the `AssetServer`, the default texture until it's loaded.
+    // Reloading the file updates the material.
+    Asset(Handle<image::DynamicImage>),
+}
+
+impl PartialEq for TextureSource {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Default, Self::Default) => true,
+            (Self::RenderTexture(a), Self::RenderTexture(b)) => a == b,
+            (Self::Image(a), Self::Image(b)) => Arc::ptr_eq(a, b),
+            (Self::Asset(a), Self::Asset(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaterialBinding {
+    // Uniform buffer visible to both stages, laid out as the shader expects.
+    Uniform(Vec<u8>),
+    // Filterable `texture_2d<f32>` followed by its `sampler`.
+    Texture(TextureSource),
+}
+
+impl MaterialBinding {
+    fn kind(&self) -> BindingKind {
+        match self {
+            MaterialBinding::Uniform(_) => BindingKind::Uniform,
+            MaterialBinding::Texture(_) => BindingKind::Texture,
+        }
+    }
+}
+
+// Material drawn with user supplied WGSL, added with `Renderer::add_material`.
+//
+// The shader has `vertex` and `fragment` entry points and sees the same inputs
+// as `assets/shaders/shader.wgsl`: the camera's `view_proj` at group 0 binding
+// 0, vertex attributes at locations 0 to 3 and the model matrix columns at 5 to
+// 8, all declared by `#import "mesh.wgsl"`. Bindings are numbered in order in
+// group 1, a texture takes two numbers, one for the texture and one for its
+// sampler. Shaders given by path are reloaded when their file, or a file they
+// include, changes in debug builds.
+//
+// Transparent materials can also have a `fragment_oit` entry point returning
+// the `OitOutput` of `#import "oit.wgsl"`, used by cameras with weighted
+// blended transparency when their state sets `oit`. Materials without one are
+// sorted by those cameras too.
+pub trait CustomMaterial: 'static {
+    fn shader(&self) -> ShaderSource;
+
+    // Defines the shader is preprocessed with. Materials sharing a shader but
+    // not its defines get separate pipelines.
+    fn defs(&self) -> ShaderDefs {
+        ShaderDefs::default()
+    }
+
+    fn bindings(&self) -> Vec<MaterialBinding> {
+        vec![]
+    }
+
+    fn state(&self) -> MaterialState {
+        MaterialState::default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum BindingKind {
+    Uniform,
+    Texture,
+}
+
+type VertexLayoutKey = (
+    wgpu::BufferAddress,
+    wgpu::VertexStepMode,
+    Vec<wgpu::VertexAttribute>,
+);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    // Hash of the preprocessed source, so each variant of a shader has its own.
+    shader: u64,
+    bindings: Vec<BindingKind>,
+    vertex_layouts: Vec<VertexLayoutKey>,
+    state: MaterialState,
+    format: wgpu::TextureFormat,
+    oit: bool,
+}
+
+// Everything a material needs from the renderer to build its pipeline and bind group.
+pub struct MaterialContext<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub format: wgpu::TextureFormat,
+    pub camera_layout: &'a wgpu::BindGroupLayout,
+    pub vertex_layouts: &'a [wgpu::VertexBufferLayout<'a>],
+    pub default_texture: &'a texture::Texture,
+    pub render_textures: &'a [RenderTexture],
+    pub images: &'a HashMap<HandleId, texture::Texture>,
+}
+
+// Shader modules, bind group layouts and pipelines shared by every material
+// with the same source, bindings, vertex layout and state.
+#[derive(Default)]
+pub struct PipelineCache {
+    shaders: HashMap<u64, wgpu::ShaderModule>,
+    layouts: HashMap<Vec<BindingKind>, wgpu::BindGroupLayout>,
+    pipelines: Vec<wgpu::RenderPipeline>,
+    keys: HashMap<PipelineKey, usize>,
+}
+
+impl PipelineCache {
+    fn layout(
+        &mut self,
+        device: &wgpu::Device,
+        bindings: &[BindingKind],
+    ) -> &wgpu::BindGroupLayout {
+        self.layouts.entry(bindings.to_vec()).or_insert_with(|| {
+            let mut entries = vec![];
+
+            for kind in bindings {
+                let binding = entries.len() as u32;
+
+                match kind {
+                    BindingKind::Uniform => entries.push(wgpu::BindGroupLayoutEntry {
+                        binding,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    }),
+                    BindingKind::Texture => entries.extend([
+                        wgpu::BindGroupLayoutEntry {
+                            binding,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: binding + 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ]),
+                }
+            }
+
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &entries,
+                label: Some("material_bind_group_layout"),
+            })
+        })
+    }
+
+    // Index of the pipeline for a material, built on first use. Failed builds
+    // aren't cached so fixing the shader retries them. `oit` pipelines draw the
+    // `fragment_oit` entry point into the accumulation targets.
+    fn pipeline(
+        &mut self,
+        context: &MaterialContext,
+        source: &str,
+        bindings: &[BindingKind],
+        state: MaterialState,
+        oit: bool,
+    ) -> Result<usize, wgpu::Error> {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+
+        let key = PipelineKey {
+            shader: hasher.finish(),
+            bindings: bindings.to_vec(),
+            vertex_layouts: context
+                .vertex_layouts
+                .iter()
+                .map(|layout| {
+                    (
+                        layout.array_stride,
+                        layout.step_mode,
+                        layout.attributes.to_vec(),
+                    )
+                })
+                .collect(),
+            state,
+            format: context.format,
+            oit,
+        };
+
+        if let Some(index) = self.keys.get(&key) {
+            return Ok(*index);
+        }
+
+        self.layout(context.device, bindings);
+
+        let shader = match self.shaders.entry(key.shader) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(validated(context.device, || {
+                context
+                    .device
+                    .create_shader_module(wgpu::ShaderModuleDescriptor {
+                        label: Some("material_shader"),
+                        source: wgpu::ShaderSource::Wgsl(Cow::Owned(source.to_string())),
+                    })
+            })?),
+        };
+        let material_layout = &self.layouts[bindings];
+
+        let pipeline_layout = create_pipeline_layout(
+            context.device,
+            Some("material_pipeline_layout"),
+            &[context.camera_layout, material_layout],
+        );
+
+        let (entry_point, targets, render_state) = match oit {
+            true => (
+                "fragment_oit",
+                oit::accum_targets().to_vec(),
+                RenderState {
+                    depth_write: false,
+                    ..state.render_state()
+                },
+            ),
+            false => (
+                "fragment",
+                vec![Some(wgpu::ColorTargetState {
+                    format: context.format,
+                    blend: Some(state.blend.blend_state()),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                state.render_state(),
+            ),
+        };
+
+        let pipeline = validated(context.device, || {
+            create_pipeline(
+                context.device,
+                Some("material_pipeline"),
+                wgpu::VertexState {
+                    module: shader,
+                    entry_point: "vertex",
+                    buffers: context.vertex_layouts,
+                },
+                wgpu::FragmentState {
+                    module: shader,
+                    entry_point,
+                    targets: &targets,
+                },
+                &pipeline_layout,
+                &render_state,
+            )
+        })?;
+
+        self.pipelines.push(pipeline);
+        self.keys.insert(key, self.pipelines.len() - 1);
+
+        Ok(self.pipelines.len() - 1)
+    }
+}
+
+struct MaterialInstance {
+    shader: ShaderSource,
+    defs: ShaderDefs,
+    // Preprocessed source the pipeline was built from.
+    source: String,
+    state: MaterialState,
+    bindings: Vec<MaterialBinding>,
+
+    // None until the shader compiles.
+    pipeline: Option<usize>,
+    // Weighted blended variant of transparent materials, None when their state
+    // doesn't set `oit`.
+    oit_pipeline: Option<usize>,
+    bind_group: wgpu::BindGroup,
+    uniform_buffers: Vec<wgpu::Buffer>,
+    // Uploads of `TextureSource::Image` bindings, None when one failed.
+    images: Vec<Option<texture::Texture>>,
+}
+
+impl MaterialInstance {
+    fn has_oit(state: &MaterialState) -> bool {
+        state.oit && state.queue() == RenderQueue::Transparent
+    }
+
+    fn samples(&self, texture: RenderTextureId) -> bool {
+        self.bindings.iter().any(|binding| {
+            *binding == MaterialBinding::Texture(TextureSource::RenderTexture(texture))
+        })
+    }
+}
+
+// Custom materials and the pipelines they share.
+#[derive(Default)]
+pub struct Materials {
+    cache: PipelineCache,
+    instances: Vec<MaterialInstance>,
+}
+
+impl Materials {
+    // `source` is the material's preprocessed shader, None when it couldn't be
+    // read; the material isn't drawn until its shader compiles.
+    pub fn add(
+        &mut self,
+        context: &MaterialContext,
+        material: &dyn CustomMaterial,
+        source: Option<String>,
+    ) -> MaterialId {
+        let instance = self.create(
+            context,
+            material.shader(),
+            material.defs(),
+            source,
+            material.state(),
+            material.bindings(),
+        );
+        self.instances.push(instance);
+
+        MaterialId(self.instances.len() as u32 - 1)
+    }
+
+    // Only uploads the uniforms when nothing else changed.
+    pub fn update(
+        &mut self,
+        context: &MaterialContext,
+        id: MaterialId,
+        material: &dyn CustomMaterial,
+        source: Option<String>,
+    ) {
+        let instance = match self.instances.get_mut(id.0 as usize) {
+            Some(instance) => instance,
+            None => return,
+        };
+
+        let shader = material.shader();
+        let defs = material.defs();
+        let state = material.state();
+        let bindings = material.bindings();
+
+        let same_layout = instance.shader == shader
+            && instance.defs == defs
+            && source.as_ref() == Some(&instance.source)
+            && instance.state == state
+            && instance.bindings.len() == bindings.len()
+            && instance
+                .bindings
+                .iter()
+                .zip(&bindings)
+                .all(|pair| match pair {
+                    (MaterialBinding::Uniform(a), MaterialBinding::Uniform(b)) => {
+                        a.len() == b.len()
+                    }
+                    (a, b) => a == b,
+                });
+
+        if same_layout {
+            let uniforms = bindings.iter().filter_map(|binding| match binding {
+                MaterialBinding::Uniform(data) => Some(data),
+                MaterialBinding::Texture(_) => None,
+            });
+
+            for (buffer, data) in instance.uniform_buffers.iter().zip(uniforms) {
+                context.queue.write_buffer(buffer, 0, data);
+            }
+
+            instance.bindings = bindings;
+            return;
+        }
+
+        // Keep drawing with the previous pipeline if the new shader fails.
+        let previous = (instance.pipeline, instance.oit_pipeline);
+        let mut recreated = self.create(context, shader, defs, source, state, bindings);
+        recreated.pipeline = recreated.pipeline.or(previous.0);
+        recreated.oit_pipeline = recreated.oit_pipeline.or(previous.1);
+
+        self.instances[id.0 as usize] = recreated;
+    }
+
+    // Render textures are recreated on resize and image assets on reload, so
+    // bind groups sampling them go stale. Rebuilds the bind groups of materials
+    // with a texture `changed` returns true for, reusing their uniform buffers
+    // and uploaded images.
+    pub fn recreate_bind_groups(
+        &mut self,
+        context: &MaterialContext,
+        changed: impl Fn(&TextureSource) -> bool,
+    ) {
+        for instance in &mut self.instances {
+            let stale = instance.bindings.iter().any(|binding| match binding {
+                MaterialBinding::Texture(source) => changed(source),
+                MaterialBinding::Uniform(_) => false,
+            });
+
+            if !stale {
+                continue;
+            }
+
+            let kinds: Vec<_> = instance
+                .bindings
+                .iter()
+                .map(MaterialBinding::kind)
+                .collect();
+
+            instance.bind_group = create_bind_group(
+                context,
+                &self.cache.layouts[&kinds],
+                &instance.bindings,
+                &instance.uniform_buffers,
+                &instance.images,
+            );
+        }
+    }
+
+    // Shader and defines of every material, in the order `reload` expects.
+    pub fn variants(&self) -> impl Iterator<Item = (&ShaderSource, &ShaderDefs)> {
+        self.instances
+            .iter()
+            .map(|instance| (&instance.shader, &instance.defs))
+    }
+
+    // Rebuilds the pipelines of materials whose preprocessed shader changed,
+    // given one source per `variants` entry. Materials whose new shader doesn't
+    // compile keep drawing with the previous one.
+    pub fn reload(&mut self, context: &MaterialContext, sources: Vec<Option<String>>) {
+        for (instance, source) in self.instances.iter_mut().zip(sources) {
+            let source = match source {
+                Some(source) if source != instance.source => source,
+                _ => continue,
+            };
+
+            let kinds: Vec<_> = instance
+                .bindings
+                .iter()
+                .map(MaterialBinding::kind)
+                .collect();
+
+            match self
+                .cache
+                .pipeline(context, &source, &kinds, instance.state, false)
+            {
+                Ok(pipeline) => instance.pipeline = Some(pipeline),
+                Err(err) => log::error!("Failed to compile {:?}: {}", instance.shader, err),
+            }
+
+            if MaterialInstance::has_oit(&instance.state) {
+                match self
+                    .cache
+                    .pipeline(context, &source, &kinds, instance.state, true)
+                {
+                    Ok(pipeline) => instance.oit_pipeline = Some(pipeline),
+                    Err(err) => log::error!("Failed to compile {:?}: {}", instance.shader, err),
+                }
+            }
+
+            instance.source = source;
+        }
+    }
+
+    // Queue of meshes drawn with the material.
+    pub fn queue(&self, id: MaterialId) -> RenderQueue {
+        self.instances
+            .get(id.0 as usize)
+            .map_or(RenderQueue::Opaque, |instance| instance.state.queue())
+    }
+
+    pub fn has_oit(&self, id: MaterialId) -> bool {
+        self.instances
+            .get(id.0 as usize)
+            .is_some_and(|instance| instance.oit_pipeline.is_some())
+    }
+
+    // Pipeline and bind group of a material, None when its shader doesn't
+    // compile or it samples `target_texture`, since a texture can't be sampled
+    // while it's being drawn to. `oit` picks the weighted blended variant.
+    pub fn get(
+        &self,
+        id: MaterialId,
+        target_texture: Option<RenderTextureId>,
+        oit: bool,
+    ) -> Option<(&wgpu::RenderPipeline, &wgpu::BindGroup)> {
+        let instance = self.instances.get(id.0 as usize)?;
+
+        if target_texture.is_some_and(|texture| instance.samples(texture)) {
+            return None;
+        }
+
+        let pipeline = match oit {
+            true => instance.oit_pipeline?,
+            false => instance.pipeline?,
+        };
+
+        Some((&self.cache.pipelines[pipeline], &instance.bind_group))
+    }
+
+    fn create(
+        &mut self,
+        context: &MaterialContext,
+        shader: ShaderSource,
+        defs: ShaderDefs,
+        source: Option<String>,
+        state: MaterialState,
+        bindings: Vec<MaterialBinding>,
+    ) -> MaterialInstance {
+        let kinds: Vec<_> = bindings.iter().map(MaterialBinding::kind).collect();
+
+        let mut build = |oit| match &source {
+            Some(source) => match self.cache.pipeline(context, source, &kinds, state, oit) {
+                Ok(pipeline) => Some(pipeline),
+                Err(err) => {
+                    log::error!("Failed to compile material shader: {}", err);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let pipeline = build(false);
+        let oit_pipeline = match MaterialInstance::has_oit(&state) {
+            true => build(true),
+            false => None,
+        };
+
+        // Bind group layouts are created along with pipelines, make sure this one
+        // exists even if the shader failed.
+        self.cache.layout(context.device, &kinds);
+
+        let mut uniform_buffers = vec![];
+        let mut images = vec![];
+
+        for binding in &bindings {
+            match binding {
+                MaterialBinding::Uniform(data) => {
+                    uniform_buffers.push(context.device.create_buffer_init(
+                        &wgpu::util::BufferInitDescriptor {
+                            label: Some("material_uniform_buffer"),
+                            contents: data,
+                            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        },
+                    ))
+                }
+                MaterialBinding::Texture(TextureSource::Image(image)) => {
+                    let texture = texture::Texture::from_image(
+                        context.device,
+                        context.queue,
+                        image,
+                        Some("material_texture"),
+                    );
+
+                    // Images that fail to upload sample the default texture.
+                    images.push(match texture {
+                        Ok(texture) => Some(texture),
+                        Err(err) => {
+                            log::error!("Failed to upload material image: {}", err);
+                            None
+                        }
+                    });
+                }
+                MaterialBinding::Texture(_) => {}
+            }
+        }
+
+        let bind_group = create_bind_group(
+            context,
+            &self.cache.layouts[&kinds],
+            &bindings,
+            &uniform_buffers,
+            &images,
+        );
+
+        MaterialInstance {
+            shader,
+            defs,
+            source: source.unwrap_or_default(),
+            state,
+            bindings,
+            pipeline,
+            oit_pipeline,
+            bind_group,
+            uniform_buffers,
+            images,
+        }
+    }
+}
+
+// Binds a material's resources in order, textures that aren't available sample
+// the default one.
+fn create_bind_group(
+    context: &MaterialContext,
+    layout: &wgpu::BindGroupLayout,
+    bindings: &[MaterialBinding],
+    uniform_buffers: &[wgpu::Buffer],
+    images: &[Option<texture::Texture>],
+) -> wgpu::BindGroup {
+    let mut entries = vec![];
+    let mut uniforms = uniform_buffers.iter();
+    let mut image_textures = images.iter();
+
+    for binding in bindings {
+        let binding_index = entries.len() as u32;
+
+        match binding {
+            MaterialBinding::Uniform(_) => entries.push(wgpu::BindGroupEntry {
+                binding: binding_index,
+                resource: uniforms.next().unwrap().as_entire_binding(),
+            }),
+            MaterialBinding::Texture(source) => {
+                let texture = match source {
+                    TextureSource::RenderTexture(id) => context
+                        .render_textures
+                        .get(id.0 as usize)
+                        .map_or(context.default_texture, |render_texture| {
+                            &render_texture.color
+                        }),
+                    TextureSource::Image(_) => image_textures
+                        .next()
+                        .unwrap()
+                        .as_ref()
+                        .unwrap_or(context.default_texture),
+                    TextureSource::Asset(handle) => context
+                        .images
+                        .get(&handle.id)
+                        .unwrap_or(context.default_texture),
+                    TextureSource::Default => context.default_texture,
+                };
+
+                entries.extend([
+                    wgpu::BindGroupEntry {
+                        binding: binding_index,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: binding_index + 1,
+                        resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                    },
+                ]);
+            }
+        }
+    }
+
+    context
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("material_bind_group"),
+        })
+}
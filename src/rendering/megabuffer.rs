@@ -0,0 +1,132 @@
+use std::{mem, ops::Range};
+
+// Growable GPU buffer mirrored by a CPU copy. Many meshes are packed into
+// one of these so they can be drawn without rebinding buffers.
+pub struct MegaBuffer<T: bytemuck::Pod> {
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+
+    data: Vec<T>,
+    dirty: Option<Range<usize>>,
+
+    buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl<T: bytemuck::Pod> MegaBuffer<T> {
+    const MIN_CAPACITY: usize = 64;
+
+    pub fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages) -> Self {
+        // Partial writes have to stay aligned to whole elements.
+        let stride = mem::size_of::<T>() as wgpu::BufferAddress;
+        assert_eq!(
+            wgpu::util::align_to(stride, wgpu::COPY_BUFFER_ALIGNMENT),
+            stride,
+            "MegaBuffer elements must be a multiple of four bytes"
+        );
+
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let capacity = Self::MIN_CAPACITY;
+        let buffer = create_buffer::<T>(device, label, usage, capacity);
+
+        Self {
+            label,
+            usage,
+            data: vec![],
+            dirty: None,
+            buffer,
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.data
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    // Appends the items and returns the index of the first one.
+    pub fn push(&mut self, items: &[T]) -> u32 {
+        let start = self.data.len();
+        self.data.extend_from_slice(items);
+        self.mark_dirty(start..self.data.len());
+
+        start as u32
+    }
+
+    // Drops the CPU copy, the GPU buffer keeps its capacity for reuse.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.dirty = None;
+    }
+
+    pub fn set(&mut self, index: usize, item: T) {
+        self.data[index] = item;
+        self.mark_dirty(index..index + 1);
+    }
+
+    // Overwrites items starting at `start`, which must already exist.
+    pub fn write(&mut self, start: usize, items: &[T]) {
+        self.data[start..start + items.len()].copy_from_slice(items);
+        self.mark_dirty(start..start + items.len());
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty = Some(match self.dirty.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    // Writes pending changes to the GPU. Returns true when the buffer had to be
+    // reallocated, in which case bind groups referencing it must be recreated.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let dirty = match self.dirty.take() {
+            Some(dirty) => dirty,
+            None => return false,
+        };
+
+        if self.data.len() > self.capacity {
+            self.capacity = self.data.len().next_power_of_two();
+            self.buffer = create_buffer::<T>(device, self.label, self.usage, self.capacity);
+
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.data));
+            return true;
+        }
+
+        let offset = (dirty.start * mem::size_of::<T>()) as wgpu::BufferAddress;
+        queue.write_buffer(
+            &self.buffer,
+            offset,
+            bytemuck::cast_slice(&self.data[dirty]),
+        );
+
+        false
+    }
+}
+
+fn create_buffer<T>(
+    device: &wgpu::Device,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    capacity: usize,
+) -> wgpu::Buffer {
+    let size = (capacity * mem::size_of::<T>()) as wgpu::BufferAddress;
+
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: wgpu::util::align_to(size, wgpu::COPY_BUFFER_ALIGNMENT),
+        usage,
+        mapped_at_creation: false,
+    })
+}
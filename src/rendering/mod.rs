@@ -1,4 +1,23 @@
+pub mod bounds;
 pub mod camera;
+pub mod culling;
+pub mod debug_view;
+pub mod frustum;
+pub mod id_buffer;
+pub mod light;
+pub mod lines;
+pub mod material;
+pub mod megabuffer;
+pub mod oit;
+pub mod pipeline;
+pub mod plugin;
+pub mod preprocessor;
+pub mod render_graph;
+pub mod render_texture;
 pub mod renderer;
+pub mod shader;
+pub mod skinning;
 pub mod texture;
 pub mod vertex;
+
+pub use plugin::RenderPlugin;
@@ -0,0 +1,133 @@
+use crate::rendering::pipeline::{create_pipeline, create_pipeline_layout, RenderState};
+
+// Transient textures of weighted blended order-independent transparency
+// (McGuire and Bavoil 2013), declared on the default render graph.
+pub const OIT_ACCUM: &str = "oit_accum";
+pub const OIT_REVEALAGE: &str = "oit_revealage";
+
+pub const ACCUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+pub const REVEALAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+// Targets of the accumulation pass, in the order of `OitOutput` in
+// `assets/shaders/oit.wgsl`. Weighted colors are summed and the revealage,
+// cleared to one, is multiplied by one minus each fragment's alpha.
+pub fn accum_targets() -> [Option<wgpu::ColorTargetState>; 2] {
+    let add = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    };
+    let reveal = wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Zero,
+        dst_factor: wgpu::BlendFactor::OneMinusSrc,
+        operation: wgpu::BlendOperation::Add,
+    };
+
+    [
+        Some(wgpu::ColorTargetState {
+            format: ACCUM_FORMAT,
+            blend: Some(wgpu::BlendState {
+                color: add,
+                alpha: add,
+            }),
+            write_mask: wgpu::ColorWrites::ALL,
+        }),
+        Some(wgpu::ColorTargetState {
+            format: REVEALAGE_FORMAT,
+            blend: Some(wgpu::BlendState {
+                color: reveal,
+                alpha: reveal,
+            }),
+            write_mask: wgpu::ColorWrites::ALL,
+        }),
+    ]
+}
+
+// Blends the average accumulated color over the view target, covering it as
+// much as the accumulated fragments together do.
+pub struct OitCompositor {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pub pipeline: wgpu::RenderPipeline,
+}
+
+impl OitCompositor {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/oit_composite.wgsl"));
+
+        // Read with `textureLoad`, so they don't need to be filterable.
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[texture_entry(0), texture_entry(1)],
+            label: Some("oit_composite_bind_group_layout"),
+        });
+
+        let pipeline = create_pipeline(
+            device,
+            Some("oit_composite_pipeline"),
+            wgpu::VertexState {
+                module: &shader,
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fragment",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            },
+            &create_pipeline_layout(
+                device,
+                Some("oit_composite_pipeline_layout"),
+                &[&bind_group_layout],
+            ),
+            &RenderState {
+                cull_mode: None,
+                depth_write: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                ..Default::default()
+            },
+        );
+
+        Self {
+            bind_group_layout,
+            pipeline,
+        }
+    }
+
+    // The graph's textures can be reallocated between views, so the bind group
+    // is made each time it's drawn.
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        accum: &wgpu::TextureView,
+        revealage: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(accum),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(revealage),
+                },
+            ],
+            label: Some("oit_composite_bind_group"),
+        })
+    }
+}
@@ -0,0 +1,92 @@
+use wgpu::BindGroupLayout;
+
+use crate::rendering::texture;
+
+// Fixed function state that differs between pipelines. The default matches
+// opaque triangle meshes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderState {
+    pub topology: wgpu::PrimitiveTopology,
+    pub polygon_mode: wgpu::PolygonMode,
+    pub cull_mode: Option<wgpu::Face>,
+    pub depth_write: bool,
+    pub depth_compare: wgpu::CompareFunction,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            cull_mode: Some(wgpu::Face::Back),
+            depth_write: true,
+            depth_compare: wgpu::CompareFunction::Less,
+        }
+    }
+}
+
+pub fn create_pipeline_layout(
+    device: &wgpu::Device,
+    label: Option<&str>,
+    bind_group_layouts: &[&BindGroupLayout],
+) -> wgpu::PipelineLayout {
+    let descriptor = wgpu::PipelineLayoutDescriptor {
+        label,
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    };
+
+    return device.create_pipeline_layout(&descriptor);
+}
+
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    label: Option<&str>,
+    vertex: wgpu::VertexState,
+    fragment: wgpu::FragmentState,
+    pipeline_layout: &wgpu::PipelineLayout,
+    state: &RenderState,
+) -> wgpu::RenderPipeline {
+    let descriptor = wgpu::RenderPipelineDescriptor {
+        label,
+        layout: Some(pipeline_layout),
+        vertex,
+        fragment: Some(fragment),
+        primitive: wgpu::PrimitiveState {
+            topology: state.topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: state.cull_mode,
+            polygon_mode: state.polygon_mode,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: state.depth_write,
+            depth_compare: state.depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    };
+
+    return device.create_render_pipeline(&descriptor);
+}
+
+// Runs `create` inside a validation error scope, so invalid shaders and
+// pipelines are returned as errors instead of panicking.
+pub fn validated<T>(device: &wgpu::Device, create: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(err) => Err(err),
+        None => Ok(value),
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+use bevy_math::Mat4;
+
+use crate::{
+    animation::MorphWeights,
+    asset::{AssetEvent, Assets, Handle},
+    debug_draw::DebugDraw,
+    loader::MeshData,
+    picking::Pickable,
+    plugin::Plugin,
+    rendering::{
+        camera::{Camera, RenderTarget, Transparency, Viewport},
+        debug_view::ViewMode,
+        material::{MaterialId, MeshMaterial},
+        render_texture::MaterialTexture,
+        renderer::{ExtractedView, MeshId, Renderer},
+        skinning::Skin,
+    },
+    scene::SceneMaterial,
+    schedule::{PostUpdate, RenderExtract},
+    time::Time,
+    transform::GlobalTransform,
+    window::{apply_window_settings, AppliedWindowSettings, WindowSettings},
+    AppBuilder,
+};
+
+// Opens the window described by `WindowSettings` and stores its `Renderer` in
+// the world as a non-send resource, along with the debug drawing resources it
+// consumes.
+pub struct RenderPlugin;
+
+impl Plugin for RenderPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<WindowSettings>();
+
+        let settings = app.world().resource::<WindowSettings>().clone();
+        let window = settings.create_window(app.event_loop());
+        let renderer = pollster::block_on(Renderer::new(window, settings.present_mode));
+
+        app.insert_non_send_resource(renderer)
+            .insert_resource(AppliedWindowSettings(settings))
+            .init_resource::<DebugDraw>()
+            .init_resource::<ViewMode>()
+            .add_asset::<MeshData>()
+            .add_asset::<image::DynamicImage>()
+            .add_asset::<SceneMaterial>()
+            .add_systems(PostUpdate, apply_window_settings)
+            .add_systems(RenderExtract, reload_shaders)
+            .add_systems(
+                RenderExtract,
+                (
+                    (remove_despawned_meshes, create_loaded_meshes).chain(),
+                    reload_meshes,
+                    extract_mesh_transforms,
+                    extract_skins,
+                    extract_morph_weights,
+                    extract_images,
+                    extract_scene_materials,
+                    extract_cameras,
+                    extract_material_textures,
+                    extract_mesh_materials,
+                    extract_view_mode,
+                    (extract_debug_lines, tick_debug_draw).chain(),
+                ),
+            );
+    }
+}
+
+type CameraQuery<'a> = (
+    Entity,
+    &'a Camera,
+    Option<&'a RenderTarget>,
+    Option<&'a Viewport>,
+    Option<&'a Transparency>,
+);
+
+pub fn extract_cameras(mut renderer: NonSendMut<Renderer>, cameras: Query<CameraQuery>) {
+    let mut views: Vec<_> = cameras
+        .iter()
+        .map(
+            |(entity, camera, target, viewport, transparency)| ExtractedView {
+                entity,
+                camera: camera.clone(),
+                target: target.copied().unwrap_or_default(),
+                viewport: viewport.copied().unwrap_or_default(),
+                transparency: transparency.copied().unwrap_or_default(),
+            },
+        )
+        .collect();
+
+    views.sort_by_key(|view| view.camera.order);
+
+    renderer.set_views(views);
+}
+
+// Uploads meshes whose asset finished loading, making them pickable.
+pub fn create_loaded_meshes(
+    mut commands: Commands,
+    mut renderer: NonSendMut<Renderer>,
+    meshes: Res<Assets<MeshData>>,
+    pending: Query<(Entity, &Handle<MeshData>, &GlobalTransform), Without<MeshId>>,
+) {
+    for (entity, handle, transform) in &pending {
+        let mesh = match meshes.get(*handle) {
+            Some(mesh) => mesh.clone(),
+            None => continue,
+        };
+
+        let mesh_id = renderer.create_mesh(&mesh, &transform.0);
+
+        commands.entity(entity).insert((
+            mesh_id,
+            Pickable {
+                mesh,
+                transform: transform.0,
+            },
+        ));
+    }
+}
+
+// Stops drawing the meshes of entities that were despawned or lost their
+// `MeshId`, remembering which entity had which mesh since removed components
+// can't be read anymore.
+pub fn remove_despawned_meshes(
+    mut renderer: NonSendMut<Renderer>,
+    added: Query<(Entity, &MeshId), Added<MeshId>>,
+    mut removed: RemovedComponents<MeshId>,
+    mut meshes: Local<HashMap<Entity, MeshId>>,
+) {
+    for entity in removed.read() {
+        if let Some(mesh_id) = meshes.remove(&entity) {
+            renderer.remove_mesh(mesh_id);
+        }
+    }
+
+    for (entity, mesh_id) in &added {
+        meshes.insert(entity, *mesh_id);
+    }
+}
+
+// Swaps the geometry of meshes whose asset was reloaded, keeping their ids.
+pub fn reload_meshes(
+    mut events: EventReader<AssetEvent<MeshData>>,
+    mut renderer: NonSendMut<Renderer>,
+    meshes: Res<Assets<MeshData>>,
+    mut loaded: Query<(&Handle<MeshData>, &MeshId, &mut Pickable)>,
+) {
+    for event in events.read() {
+        let handle = match event {
+            AssetEvent::Modified(handle) => *handle,
+            AssetEvent::Loaded(_) => continue,
+        };
+
+        let mesh = match meshes.get(handle) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+
+        for (_, mesh_id, mut pickable) in loaded.iter_mut().filter(|(h, ..)| **h == handle) {
+            renderer.update_mesh(*mesh_id, mesh);
+            pickable.mesh = mesh.clone();
+        }
+    }
+}
+
+// Moves meshes whose `GlobalTransform` was recomputed in `PostUpdate`.
+pub fn extract_mesh_transforms(
+    mut renderer: NonSendMut<Renderer>,
+    mut moved: Query<(&MeshId, &GlobalTransform, Option<&mut Pickable>), Changed<GlobalTransform>>,
+) {
+    for (mesh_id, transform, pickable) in &mut moved {
+        renderer.set_mesh_transform(*mesh_id, &transform.0);
+
+        if let Some(mut pickable) = pickable {
+            pickable.transform = transform.0;
+        }
+    }
+}
+
+// Poses skinned meshes from their joints' `GlobalTransform`, relative to the
+// mesh since the renderer still applies the mesh's own transform.
+pub fn extract_skins(
+    mut renderer: NonSendMut<Renderer>,
+    skinned: Query<(&MeshId, &Skin, &GlobalTransform)>,
+    joints: Query<&GlobalTransform>,
+) {
+    for (mesh_id, skin, transform) in &skinned {
+        let inverse = transform.0.inverse();
+
+        let matrices: Vec<_> = skin
+            .joints
+            .iter()
+            .zip(skin.inverse_bind_matrices.iter())
+            .map(|(joint, inverse_bind)| match joints.get(*joint) {
+                Ok(joint) => inverse * joint.0 * *inverse_bind,
+                Err(_) => Mat4::IDENTITY,
+            })
+            .collect();
+
+        renderer.set_mesh_joints(*mesh_id, &matrices);
+    }
+}
+
+type ChangedWeights = Or<(Changed<MorphWeights>, Added<MeshId>)>;
+
+// Weights of new meshes are extracted too, they may have been set before the
+// mesh was created.
+pub fn extract_morph_weights(
+    mut renderer: NonSendMut<Renderer>,
+    changed: Query<(&MeshId, &MorphWeights), ChangedWeights>,
+) {
+    for (mesh_id, weights) in &changed {
+        renderer.set_mesh_morph_weights(*mesh_id, &weights.0);
+    }
+}
+
+// Uploads image assets, materials sampling them are updated in place.
+pub fn extract_images(
+    mut events: EventReader<AssetEvent<image::DynamicImage>>,
+    mut renderer: NonSendMut<Renderer>,
+    images: Res<Assets<image::DynamicImage>>,
+) {
+    for event in events.read() {
+        let handle = match event {
+            AssetEvent::Loaded(handle) | AssetEvent::Modified(handle) => *handle,
+        };
+
+        if let Some(image) = images.get(handle) {
+            renderer.set_image(handle, image);
+        }
+    }
+}
+
+// Gives meshes with a scene material the renderer material made from it, one
+// per material asset. Reloaded materials are updated in place.
+pub fn extract_scene_materials(
+    mut commands: Commands,
+    mut renderer: NonSendMut<Renderer>,
+    mut events: EventReader<AssetEvent<SceneMaterial>>,
+    materials: Res<Assets<SceneMaterial>>,
+    pending: Query<(Entity, &Handle<SceneMaterial>), Without<MeshMaterial>>,
+    meshes: Query<&MeshId>,
+    mut created: Local<HashMap<Handle<SceneMaterial>, MaterialId>>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Modified(handle) = event {
+            if let (Some(id), Some(material)) = (created.get(handle), materials.get(*handle)) {
+                renderer.update_material(*id, material.as_ref());
+            }
+        }
+    }
+
+    for (entity, handle) in &pending {
+        // `MeshMaterial` only reaches meshes that exist when it's inserted.
+        let material = match materials.get(*handle) {
+            Some(material) if meshes.contains(entity) => material,
+            _ => continue,
+        };
+
+        let id = *created
+            .entry(*handle)
+            .or_insert_with(|| renderer.add_material(material.as_ref()));
+
+        commands.entity(entity).insert(MeshMaterial(id));
+    }
+}
+
+pub fn extract_material_textures(
+    mut renderer: NonSendMut<Renderer>,
+    changed: Query<(&MeshId, &MaterialTexture), Changed<MaterialTexture>>,
+    mut removed: RemovedComponents<MaterialTexture>,
+    meshes: Query<&MeshId>,
+) {
+    for entity in removed.read() {
+        if let Ok(mesh) = meshes.get(entity) {
+            renderer.set_mesh_texture(*mesh, None);
+        }
+    }
+
+    for (mesh, texture) in &changed {
+        renderer.set_mesh_texture(*mesh, Some(texture.0));
+    }
+}
+
+pub fn extract_mesh_materials(
+    mut renderer: NonSendMut<Renderer>,
+    changed: Query<(&MeshId, &MeshMaterial), Changed<MeshMaterial>>,
+    mut removed: RemovedComponents<MeshMaterial>,
+    meshes: Query<&MeshId>,
+) {
+    for entity in removed.read() {
+        if let Ok(mesh) = meshes.get(entity) {
+            renderer.set_mesh_material(*mesh, None);
+        }
+    }
+
+    for (mesh, material) in &changed {
+        renderer.set_mesh_material(*mesh, Some(material.0));
+    }
+}
+
+// Shaders are only watched in debug builds, this does nothing otherwise.
+pub fn reload_shaders(mut renderer: NonSendMut<Renderer>) {
+    renderer.reload_shaders();
+}
+
+pub fn extract_view_mode(mut renderer: NonSendMut<Renderer>, view_mode: Res<ViewMode>) {
+    renderer.set_view_mode(*view_mode);
+}
+
+pub fn extract_debug_lines(mut renderer: NonSendMut<Renderer>, debug_draw: Res<DebugDraw>) {
+    renderer.prepare_debug_lines(&debug_draw);
+}
+
+// Debug lines age in real time so they stay visible while paused.
+pub fn tick_debug_draw(mut debug_draw: ResMut<DebugDraw>, time: Res<Time>) {
+    debug_draw.tick(time.raw_delta_seconds());
+}
+
+// Draws the views extracted during `RenderExtract` to every window. Lost or
+// outdated surfaces are reconfigured by the renderer, other surface errors are
+// returned.
+pub fn render_frame(world: &mut World) -> Result<(), wgpu::SurfaceError> {
+    Renderer::scope(world, |_, renderer| renderer.render()).unwrap_or(Ok(()))
+}
@@ -0,0 +1,436 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
+
+// Defines a shader is compiled with, each set is a separate variant with its
+// own pipeline. Defines without a value only enable `#ifdef` blocks, the others
+// also replace their name in the source.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderDefs(BTreeMap<String, String>);
+
+impl ShaderDefs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str) -> Self {
+        self.set(name, "");
+        self
+    }
+
+    pub fn with_value(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    pub fn set(&mut self, name: &str, value: impl fmt::Display) {
+        self.0.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreprocessError {
+    pub file: String,
+    // Starts at 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+// Expands the directives of a WGSL source:
+//
+//   #include "path"     pastes the file every time
+//   #import "path"      pastes the file once per shader, for shared modules
+//   #define NAME [value]
+//   #undef NAME
+//   #ifdef NAME, #ifndef NAME, #else, #endif
+//
+// `load` reads included files by path, `name` is only used in errors.
+pub fn preprocess(
+    name: &str,
+    source: &str,
+    defs: &ShaderDefs,
+    load: &mut dyn FnMut(&str) -> Result<String, String>,
+) -> Result<String, PreprocessError> {
+    let mut preprocessor = Preprocessor {
+        defines: defs.0.clone(),
+        imported: HashSet::new(),
+        stack: vec![],
+        output: String::with_capacity(source.len()),
+        load,
+    };
+
+    preprocessor.process(name, source)?;
+
+    Ok(preprocessor.output)
+}
+
+struct Condition {
+    // Line of the `#ifdef`, for unterminated blocks.
+    line: usize,
+    parent_active: bool,
+    taken: bool,
+    active: bool,
+    seen_else: bool,
+}
+
+struct Preprocessor<'a> {
+    defines: BTreeMap<String, String>,
+    imported: HashSet<String>,
+    // Files being processed, to catch includes of a file from itself.
+    stack: Vec<String>,
+    output: String,
+    load: &'a mut dyn FnMut(&str) -> Result<String, String>,
+}
+
+impl Preprocessor<'_> {
+    fn process(&mut self, file: &str, source: &str) -> Result<(), PreprocessError> {
+        self.stack.push(file.to_string());
+
+        let mut conditions: Vec<Condition> = vec![];
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |message: String| PreprocessError {
+                file: file.to_string(),
+                line: line_number,
+                message,
+            };
+
+            let active = conditions.iter().all(|condition| condition.active);
+
+            let directive = match line.trim_start().strip_prefix('#') {
+                Some(directive) => directive,
+                None => {
+                    if active {
+                        self.output.push_str(&substitute(line, &self.defines));
+                        self.output.push('\n');
+                    }
+                    continue;
+                }
+            };
+
+            let (keyword, argument) = match directive.split_once(char::is_whitespace) {
+                Some((keyword, argument)) => (keyword, argument.trim()),
+                None => (directive.trim(), ""),
+            };
+
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let name = identifier(argument).map_err(error)?;
+                    let taken = self.defines.contains_key(name) == (keyword == "ifdef");
+
+                    conditions.push(Condition {
+                        line: line_number,
+                        parent_active: active,
+                        taken,
+                        active: active && taken,
+                        seen_else: false,
+                    });
+                }
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .ok_or_else(|| error("#else without #ifdef".to_string()))?;
+
+                    if condition.seen_else {
+                        return Err(error("Duplicate #else".to_string()));
+                    }
+
+                    condition.seen_else = true;
+                    condition.active = condition.parent_active && !condition.taken;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| error("#endif without #ifdef".to_string()))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (name, value) = match argument.split_once(char::is_whitespace) {
+                        Some((name, value)) => (name, value.trim()),
+                        None => (argument, ""),
+                    };
+
+                    let name = identifier(name).map_err(error)?;
+                    self.defines.insert(name.to_string(), value.to_string());
+                }
+                "undef" => {
+                    let name = identifier(argument).map_err(error)?;
+                    self.defines.remove(name);
+                }
+                "include" | "import" => {
+                    let path = argument
+                        .strip_prefix('"')
+                        .and_then(|path| path.strip_suffix('"'))
+                        .ok_or_else(|| {
+                            error(format!("Expected a quoted path after #{}", keyword))
+                        })?;
+
+                    if keyword == "import" && !self.imported.insert(path.to_string()) {
+                        continue;
+                    }
+
+                    if self.stack.iter().any(|file| file == path) {
+                        return Err(error(format!("{} includes itself", path)));
+                    }
+
+                    let source = (self.load)(path)
+                        .map_err(|err| error(format!("Can't include {}: {}", path, err)))?;
+
+                    self.process(path, &source)?;
+                }
+                _ => return Err(error(format!("Unknown directive #{}", keyword))),
+            }
+        }
+
+        if let Some(condition) = conditions.last() {
+            return Err(PreprocessError {
+                file: file.to_string(),
+                line: condition.line,
+                message: "Unterminated #ifdef".to_string(),
+            });
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+fn identifier(argument: &str) -> Result<&str, String> {
+    let valid = argument
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && argument
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    match valid {
+        true => Ok(argument),
+        false => Err(format!("Expected a name, found '{}'", argument)),
+    }
+}
+
+// Replaces whole identifiers that name a define with a value.
+fn substitute(line: &str, defines: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+
+        match defines.get(word) {
+            Some(value) if !value.is_empty() => output.push_str(value),
+            _ => output.push_str(word),
+        }
+
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn run(
+        source: &str,
+        defs: &ShaderDefs,
+        files: &[(&str, &str)],
+    ) -> Result<String, PreprocessError> {
+        let files: HashMap<_, _> = files.iter().copied().collect();
+        let mut load = |path: &str| match files.get(path) {
+            Some(source) => Ok(source.to_string()),
+            None => Err("not found".to_string()),
+        };
+
+        preprocess("main.wgsl", source, defs, &mut load)
+    }
+
+    fn error(source: &str, files: &[(&str, &str)]) -> PreprocessError {
+        run(source, &ShaderDefs::new(), files).unwrap_err()
+    }
+
+    #[test]
+    fn nested_conditions() {
+        let source = "\
+#ifdef A
+a
+#ifndef B
+not b
+#else
+b
+#endif
+#else
+not a
+#ifdef B
+unreachable
+#endif
+#endif
+end";
+
+        assert_eq!(
+            run(source, &ShaderDefs::new(), &[]).unwrap(),
+            "not a\nend\n"
+        );
+        assert_eq!(
+            run(source, &ShaderDefs::new().with("A"), &[]).unwrap(),
+            "a\nnot b\nend\n"
+        );
+        assert_eq!(
+            run(source, &ShaderDefs::new().with("A").with("B"), &[]).unwrap(),
+            "a\nb\nend\n"
+        );
+        assert_eq!(
+            run(source, &ShaderDefs::new().with("B"), &[]).unwrap(),
+            "not a\nunreachable\nend\n"
+        );
+    }
+
+    #[test]
+    fn define_and_undef() {
+        let source = "\
+#define SIZE 4
+#define FLAG
+let a = SIZE * SIZE_2 + COUNT;
+#ifdef FLAG
+flag
+#endif
+#undef SIZE
+#undef FLAG
+let b = SIZE;
+#ifdef FLAG
+flag
+#endif";
+
+        assert_eq!(
+            run(source, &ShaderDefs::new().with_value("COUNT", 3), &[]).unwrap(),
+            "let a = 4 * SIZE_2 + 3;\nflag\nlet b = SIZE;\n"
+        );
+    }
+
+    #[test]
+    fn defines_in_inactive_blocks_are_ignored() {
+        let source = "\
+#ifdef MISSING
+#define VALUE 1
+#endif
+VALUE";
+
+        assert_eq!(run(source, &ShaderDefs::new(), &[]).unwrap(), "VALUE\n");
+    }
+
+    #[test]
+    fn import_once_include_every_time() {
+        let files = [("common.wgsl", "common"), ("part.wgsl", "part")];
+        let source = "\
+#import \"common.wgsl\"
+#include \"part.wgsl\"
+#import \"common.wgsl\"
+#include \"part.wgsl\"";
+
+        assert_eq!(
+            run(source, &ShaderDefs::new(), &files).unwrap(),
+            "common\npart\npart\n"
+        );
+    }
+
+    #[test]
+    fn imports_are_shared_by_included_files() {
+        let files = [
+            ("common.wgsl", "common"),
+            ("a.wgsl", "#import \"common.wgsl\"\na"),
+        ];
+        let source = "#include \"a.wgsl\"\n#import \"common.wgsl\"";
+
+        assert_eq!(
+            run(source, &ShaderDefs::new(), &files).unwrap(),
+            "common\na\n"
+        );
+    }
+
+    #[test]
+    fn recursive_include() {
+        let files = [
+            ("a.wgsl", "#include \"b.wgsl\""),
+            ("b.wgsl", "#include \"a.wgsl\""),
+        ];
+        let err = error("#include \"a.wgsl\"", &files);
+
+        assert_eq!(err.file, "b.wgsl");
+        assert_eq!(err.line, 1);
+        assert_eq!(err.message, "a.wgsl includes itself");
+    }
+
+    #[test]
+    fn missing_include() {
+        let err = error("\n#include \"missing.wgsl\"", &[]);
+
+        assert_eq!(err.file, "main.wgsl");
+        assert_eq!(err.line, 2);
+        assert_eq!(err.message, "Can't include missing.wgsl: not found");
+    }
+
+    #[test]
+    fn unbalanced_conditions() {
+        let err = error("a\n#endif", &[]);
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "#endif without #ifdef")
+        );
+
+        let err = error("#else", &[]);
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (1, "#else without #ifdef")
+        );
+
+        let err = error("#ifdef A\n#else\n#else\n#endif", &[]);
+        assert_eq!((err.line, err.message.as_str()), (3, "Duplicate #else"));
+
+        let err = error("#ifdef A\n#ifdef B\n#endif", &[]);
+        assert_eq!((err.line, err.message.as_str()), (1, "Unterminated #ifdef"));
+    }
+
+    #[test]
+    fn invalid_directives() {
+        let err = error("a\n#pragma once", &[]);
+        assert_eq!(
+            (err.line, err.message.as_str()),
+            (2, "Unknown directive #pragma")
+        );
+
+        let err = error("#define 1A", &[]);
+        assert_eq!(err.message, "Expected a name, found '1A'");
+
+        let err = error("#include common.wgsl", &[]);
+        assert_eq!(err.message, "Expected a quoted path after #include");
+    }
+}
@@ -0,0 +1,607 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use bevy_math::prelude::*;
+
+use crate::rendering::{
+    camera::{Camera, Transparency},
+    render_texture::RenderTextureId,
+    renderer::Renderer,
+};
+
+// Slots imported from the view being drawn, every node can use them without
+// declaring them on the graph.
+pub const VIEW_TARGET: &str = "view_target";
+pub const VIEW_DEPTH: &str = "view_depth";
+
+// Names of the nodes drawing the scene, custom nodes can be ordered around them.
+pub const MAIN_PASS: &str = "main_pass";
+pub const OIT_ACCUMULATE: &str = "oit_accumulate";
+pub const OIT_COMPOSITE: &str = "oit_composite";
+pub const DEBUG_LINES: &str = "debug_lines";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureSize {
+    // Follows the size of the view's render target.
+    Target,
+    Fixed(UVec2),
+}
+
+// Transient texture allocated by the graph and shared by the nodes using it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureSlot {
+    pub format: wgpu::TextureFormat,
+    pub size: TextureSize,
+    pub usage: wgpu::TextureUsages,
+}
+
+impl TextureSlot {
+    fn extent(&self, target_size: UVec2) -> UVec2 {
+        match self.size {
+            TextureSize::Target => target_size,
+            TextureSize::Fixed(size) => size,
+        }
+        .max(UVec2::ONE)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferSlot {
+    pub size: wgpu::BufferAddress,
+    pub usage: wgpu::BufferUsages,
+}
+
+// What nodes know about the view the graph is drawing.
+pub struct ViewInfo {
+    pub camera: Camera,
+    // Viewport inside the render target, in pixels.
+    pub offset: UVec2,
+    pub extent: UVec2,
+    // Size of the render target in pixels.
+    pub size: UVec2,
+    // True for the first view drawn to its target this frame, which clears it.
+    pub first: bool,
+    // Render texture drawn to, None for windows.
+    pub texture: Option<RenderTextureId>,
+    pub transparency: Transparency,
+}
+
+// Step of the graph. Nodes declare the slots they read and write; writers of a
+// slot run in the order they were added, before every node only reading it.
+pub trait Node: 'static {
+    fn inputs(&self) -> &[&str] {
+        &[]
+    }
+
+    fn outputs(&self) -> &[&str] {
+        &[]
+    }
+
+    // Inactive nodes don't run for the view, and slots only they use aren't
+    // allocated for it.
+    fn is_active(&self, _renderer: &Renderer, _view: &ViewInfo) -> bool {
+        true
+    }
+
+    fn run(&mut self, context: &NodeContext, encoder: &mut wgpu::CommandEncoder);
+}
+
+pub struct NodeContext<'a> {
+    pub renderer: &'a Renderer,
+    pub view: &'a ViewInfo,
+    textures: HashMap<&'a str, &'a wgpu::TextureView>,
+    buffers: HashMap<&'a str, &'a wgpu::Buffer>,
+}
+
+impl<'a> NodeContext<'a> {
+    pub fn texture(&self, name: &str) -> &'a wgpu::TextureView {
+        self.textures
+            .get(name)
+            .unwrap_or_else(|| panic!("Texture slot {} is not declared", name))
+    }
+
+    pub fn buffer(&self, name: &str) -> &'a wgpu::Buffer {
+        self.buffers
+            .get(name)
+            .unwrap_or_else(|| panic!("Buffer slot {} is not declared", name))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenderGraphError {
+    DuplicateNode(String),
+    UnknownNode(String),
+    MissingSlot { node: String, slot: String },
+    // Nodes left over when ordering stopped.
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DuplicateNode(name) => write!(f, "Node {} already exists", name),
+            Self::UnknownNode(name) => write!(f, "Node {} doesn't exist", name),
+            Self::MissingSlot { node, slot } => {
+                write!(f, "Node {} uses undeclared slot {}", node, slot)
+            }
+            Self::Cycle(nodes) => write!(f, "Nodes {} depend on each other", nodes.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
+
+struct NodeEntry {
+    name: String,
+    node: Box<dyn Node>,
+}
+
+struct TransientTexture {
+    // Kept alive alongside its view.
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    used: bool,
+}
+
+// Orders the passes drawing a view and owns their transient attachments.
+// Textures are allocated per size, so views of different sizes don't fight
+// over them, and are freed once no view of their size was drawn in a frame.
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes: Vec<NodeEntry>,
+    edges: Vec<(String, String)>,
+
+    textures: HashMap<String, TextureSlot>,
+    buffers: HashMap<String, BufferSlot>,
+
+    // Indices into `nodes`, None until the graph changes.
+    order: Option<Result<Vec<usize>, RenderGraphError>>,
+
+    transient_textures: HashMap<(String, UVec2), TransientTexture>,
+    transient_buffers: HashMap<String, wgpu::Buffer>,
+}
+
+impl RenderGraph {
+    pub fn add_node(&mut self, name: &str, node: impl Node) -> Result<(), RenderGraphError> {
+        if self.nodes.iter().any(|entry| entry.name == name) {
+            return Err(RenderGraphError::DuplicateNode(name.to_string()));
+        }
+
+        self.nodes.push(NodeEntry {
+            name: name.to_string(),
+            node: Box::new(node),
+        });
+        self.order = None;
+
+        Ok(())
+    }
+
+    pub fn remove_node(&mut self, name: &str) -> Option<Box<dyn Node>> {
+        let index = self.nodes.iter().position(|entry| entry.name == name)?;
+
+        self.edges
+            .retain(|(before, after)| before != name && after != name);
+        self.order = None;
+
+        Some(self.nodes.remove(index).node)
+    }
+
+    // Runs `before` ahead of `after` when they share no slot.
+    pub fn add_edge(&mut self, before: &str, after: &str) {
+        self.edges.push((before.to_string(), after.to_string()));
+        self.order = None;
+    }
+
+    // Replacing a slot drops its allocations, they're recreated on next use.
+    pub fn add_texture(&mut self, name: &str, slot: TextureSlot) {
+        self.transient_textures
+            .retain(|(slot_name, _), _| slot_name != name);
+        self.textures.insert(name.to_string(), slot);
+        self.order = None;
+    }
+
+    pub fn add_buffer(&mut self, name: &str, slot: BufferSlot) {
+        self.transient_buffers.remove(name);
+        self.buffers.insert(name.to_string(), slot);
+        self.order = None;
+    }
+
+    // Node names in the order they run.
+    pub fn order(&mut self) -> Result<Vec<&str>, RenderGraphError> {
+        let order = self.order.get_or_insert_with(|| {
+            sort(&self.nodes, &self.edges, |slot| {
+                self.textures.contains_key(slot) || self.buffers.contains_key(slot)
+            })
+        });
+
+        match order {
+            Ok(order) => Ok(order
+                .iter()
+                .map(|index| self.nodes[*index].name.as_str())
+                .collect()),
+            Err(err) => Err(err.clone()),
+        }
+    }
+
+    // Draws a view, recording every node into `encoder`. An invalid graph is
+    // reported once when it changes and draws nothing.
+    pub fn run(
+        &mut self,
+        renderer: &Renderer,
+        view: &ViewInfo,
+        target: &wgpu::TextureView,
+        depth: &wgpu::TextureView,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if self.order.is_none() {
+            if let Err(err) = self.order() {
+                log::error!("Invalid render graph: {}", err);
+            }
+        }
+
+        let order = match &self.order {
+            Some(Ok(order)) => order,
+            _ => return,
+        };
+
+        let active: Vec<usize> = order
+            .iter()
+            .copied()
+            .filter(|index| self.nodes[*index].node.is_active(renderer, view))
+            .collect();
+
+        let used: HashSet<&str> = active
+            .iter()
+            .flat_map(|index| {
+                let node = &self.nodes[*index].node;
+                node.inputs().iter().chain(node.outputs())
+            })
+            .copied()
+            .collect();
+
+        let device = renderer.device();
+
+        let textures: Vec<(&String, &TextureSlot)> = self
+            .textures
+            .iter()
+            .filter(|(name, _)| used.contains(name.as_str()))
+            .collect();
+
+        for (name, slot) in &textures {
+            let size = slot.extent(view.size);
+
+            self.transient_textures
+                .entry((name.to_string(), size))
+                .or_insert_with(|| create_texture(device, name, slot, size))
+                .used = true;
+        }
+
+        for (name, slot) in &self.buffers {
+            self.transient_buffers
+                .entry(name.clone())
+                .or_insert_with(|| {
+                    device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some(name),
+                        size: slot.size,
+                        usage: slot.usage,
+                        mapped_at_creation: false,
+                    })
+                });
+        }
+
+        let mut views = HashMap::from([(VIEW_TARGET, target), (VIEW_DEPTH, depth)]);
+
+        for (name, slot) in textures {
+            let size = slot.extent(view.size);
+
+            views.insert(
+                name.as_str(),
+                &self.transient_textures[&(name.clone(), size)].view,
+            );
+        }
+
+        let context = NodeContext {
+            renderer,
+            view,
+            textures: views,
+            buffers: self
+                .transient_buffers
+                .iter()
+                .map(|(name, buffer)| (name.as_str(), buffer))
+                .collect(),
+        };
+
+        for index in active {
+            self.nodes[index].node.run(&context, encoder);
+        }
+    }
+
+    // Frees the textures of sizes no view used since the last call.
+    pub fn end_frame(&mut self) {
+        self.transient_textures.retain(|_, texture| texture.used);
+
+        for texture in self.transient_textures.values_mut() {
+            texture.used = false;
+        }
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    name: &str,
+    slot: &TextureSlot,
+    size: UVec2,
+) -> TransientTexture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(name),
+        size: wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: slot.format,
+        usage: slot.usage,
+        view_formats: &[],
+    });
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    TransientTexture {
+        _texture: texture,
+        view,
+        used: false,
+    }
+}
+
+// Topological order of the nodes, ties broken by the order nodes were added.
+fn sort(
+    nodes: &[NodeEntry],
+    edges: &[(String, String)],
+    is_declared: impl Fn(&str) -> bool,
+) -> Result<Vec<usize>, RenderGraphError> {
+    let index_of = |name: &str| {
+        nodes
+            .iter()
+            .position(|entry| entry.name == name)
+            .ok_or_else(|| RenderGraphError::UnknownNode(name.to_string()))
+    };
+
+    let mut writers: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut readers: HashMap<&str, Vec<usize>> = HashMap::new();
+
+    for (index, entry) in nodes.iter().enumerate() {
+        let slots = entry.node.inputs().iter().chain(entry.node.outputs());
+
+        for slot in slots {
+            if *slot != VIEW_TARGET && *slot != VIEW_DEPTH && !is_declared(slot) {
+                return Err(RenderGraphError::MissingSlot {
+                    node: entry.name.clone(),
+                    slot: slot.to_string(),
+                });
+            }
+        }
+
+        for slot in entry.node.outputs() {
+            writers.entry(slot).or_default().push(index);
+        }
+
+        for slot in entry.node.inputs() {
+            if !entry.node.outputs().contains(slot) {
+                readers.entry(slot).or_default().push(index);
+            }
+        }
+    }
+
+    let mut dependencies = HashSet::new();
+
+    for (slot, writers) in &writers {
+        for pair in writers.windows(2) {
+            dependencies.insert((pair[0], pair[1]));
+        }
+
+        for reader in readers.get(slot).into_iter().flatten() {
+            for writer in writers {
+                dependencies.insert((*writer, *reader));
+            }
+        }
+    }
+
+    for (before, after) in edges {
+        dependencies.insert((index_of(before)?, index_of(after)?));
+    }
+
+    let mut incoming = vec![0; nodes.len()];
+    for (_, after) in &dependencies {
+        incoming[*after] += 1;
+    }
+
+    let mut ready: BTreeSet<usize> = (0..nodes.len())
+        .filter(|index| incoming[*index] == 0)
+        .collect();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    while let Some(index) = ready.pop_first() {
+        order.push(index);
+
+        for (before, after) in &dependencies {
+            if *before == index {
+                incoming[*after] -= 1;
+                if incoming[*after] == 0 {
+                    ready.insert(*after);
+                }
+            }
+        }
+    }
+
+    if order.len() < nodes.len() {
+        let remaining = (0..nodes.len())
+            .filter(|index| !order.contains(index))
+            .map(|index| nodes[index].name.clone())
+            .collect();
+
+        return Err(RenderGraphError::Cycle(remaining));
+    }
+
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestNode {
+        inputs: &'static [&'static str],
+        outputs: &'static [&'static str],
+    }
+
+    impl Node for TestNode {
+        fn inputs(&self) -> &[&str] {
+            self.inputs
+        }
+
+        fn outputs(&self) -> &[&str] {
+            self.outputs
+        }
+
+        fn run(&mut self, _context: &NodeContext, _encoder: &mut wgpu::CommandEncoder) {}
+    }
+
+    fn node(inputs: &'static [&'static str], outputs: &'static [&'static str]) -> TestNode {
+        TestNode { inputs, outputs }
+    }
+
+    fn graph_with<const N: usize>(nodes: [(&str, TestNode); N]) -> RenderGraph {
+        let mut graph = RenderGraph::default();
+
+        for name in ["shadow", "bloom"] {
+            graph.add_texture(
+                name,
+                TextureSlot {
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    size: TextureSize::Target,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                },
+            );
+        }
+
+        for (name, node) in nodes {
+            graph.add_node(name, node).unwrap();
+        }
+
+        graph
+    }
+
+    #[test]
+    fn readers_run_after_writers() {
+        let mut graph = graph_with([
+            ("post", node(&["bloom"], &[])),
+            ("main", node(&["shadow"], &[VIEW_TARGET, "bloom"])),
+            ("shadows", node(&[], &["shadow"])),
+        ]);
+
+        assert_eq!(graph.order().unwrap(), ["shadows", "main", "post"]);
+    }
+
+    #[test]
+    fn writers_run_in_the_order_they_were_added() {
+        let mut graph = graph_with([
+            ("c", node(&[], &[VIEW_TARGET])),
+            ("a", node(&[], &[VIEW_TARGET])),
+            ("b", node(&[], &[VIEW_TARGET])),
+        ]);
+
+        assert_eq!(graph.order().unwrap(), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn independent_nodes_keep_their_order() {
+        let mut graph = graph_with([
+            ("c", node(&[], &["shadow"])),
+            ("a", node(&[], &["bloom"])),
+            ("b", node(&[], &[])),
+        ]);
+        assert_eq!(graph.order().unwrap(), ["c", "a", "b"]);
+
+        // Only the edge moves "b", the others stay in insertion order.
+        graph.add_edge("b", "a");
+        assert_eq!(graph.order().unwrap(), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut graph = graph_with([
+            ("first", node(&[], &[])),
+            ("a", node(&["shadow"], &["bloom"])),
+            ("b", node(&["bloom"], &["shadow"])),
+        ]);
+
+        assert_eq!(
+            graph.order(),
+            Err(RenderGraphError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn edges_can_make_cycles() {
+        let mut graph = graph_with([("a", node(&[], &["shadow"])), ("b", node(&["shadow"], &[]))]);
+        graph.add_edge("b", "a");
+
+        assert_eq!(
+            graph.order(),
+            Err(RenderGraphError::Cycle(vec![
+                "a".to_string(),
+                "b".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn removing_a_node_removes_its_edges() {
+        let mut graph = graph_with([
+            ("a", node(&[], &[])),
+            ("b", node(&[], &[])),
+            ("c", node(&[], &[])),
+        ]);
+        graph.add_edge("c", "a");
+        graph.add_edge("b", "c");
+        graph.add_edge("a", "b");
+        assert!(matches!(graph.order(), Err(RenderGraphError::Cycle(_))));
+
+        assert!(graph.remove_node("b").is_some());
+        assert!(graph.remove_node("b").is_none());
+        assert_eq!(graph.order().unwrap(), ["c", "a"]);
+    }
+
+    #[test]
+    fn unknown_nodes_and_slots() {
+        let mut graph = graph_with([("a", node(&[], &[]))]);
+        graph.add_edge("a", "missing");
+        assert_eq!(
+            graph.order(),
+            Err(RenderGraphError::UnknownNode("missing".to_string()))
+        );
+
+        let mut graph = graph_with([("a", node(&["missing"], &[]))]);
+        assert_eq!(
+            graph.order(),
+            Err(RenderGraphError::MissingSlot {
+                node: "a".to_string(),
+                slot: "missing".to_string(),
+            })
+        );
+
+        let mut graph = graph_with([("a", node(&[], &[]))]);
+        assert_eq!(
+            graph.add_node("a", node(&[], &[])),
+            Err(RenderGraphError::DuplicateNode("a".to_string()))
+        );
+    }
+}
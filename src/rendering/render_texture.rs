@@ -0,0 +1,74 @@
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+use crate::rendering::texture;
+
+// Offscreen texture created by `Renderer::create_render_texture`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderTextureId(pub u32);
+
+// Makes a mesh sample a render texture instead of its default texture. The
+// texture shows what its cameras drew this frame when they come earlier in
+// camera order, otherwise what they drew the frame before.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaterialTexture(pub RenderTextureId);
+
+// Color and depth buffers of an offscreen render target, with a bind group laid
+// out like the main material's so meshes can sample it.
+pub struct RenderTexture {
+    pub color: texture::Texture,
+    pub depth: texture::Texture,
+    pub bind_group: wgpu::BindGroup,
+    size: UVec2,
+}
+
+impl RenderTexture {
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        size: UVec2,
+    ) -> Self {
+        let size = size.max(UVec2::ONE);
+
+        let color = texture::Texture::create_render_texture(
+            device,
+            size.x,
+            size.y,
+            format,
+            "render_texture",
+        );
+        let depth = texture::Texture::create_depth_texture_with_size(
+            device,
+            size.x,
+            size.y,
+            "render_texture_depth",
+        );
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&color.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&color.sampler),
+                },
+            ],
+            label: Some("render_texture_bind_group"),
+        });
+
+        Self {
+            color,
+            depth,
+            bind_group,
+            size,
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+}
@@ -1,17 +1,89 @@
-use crate::rendering::{camera::Camera, texture, vertex::Vertex};
+use crate::{
+    asset::{Handle, HandleId},
+    debug_draw::DebugDraw,
+    loader::MeshData,
+    rendering::{
+        bounds::{Aabb, BoundingSphere},
+        camera::{Camera, RenderTarget, Transparency, Viewport},
+        culling::{self, DrawData, GpuCulling, INDIRECT_STRIDE},
+        debug_view::{DebugViewRenderer, ViewMode},
+        frustum::Frustum,
+        id_buffer::{self, IdBuffer},
+        lines::LineRenderer,
+        material::{
+            CustomMaterial, MaterialContext, MaterialId, Materials, RenderQueue, TextureSource,
+        },
+        megabuffer::MegaBuffer,
+        oit::{self, OitCompositor, OIT_ACCUM, OIT_REVEALAGE},
+        pipeline::{create_pipeline, create_pipeline_layout, validated, RenderState},
+        preprocessor::ShaderDefs,
+        render_graph::{
+            Node, NodeContext, RenderGraph, TextureSize, TextureSlot, ViewInfo, DEBUG_LINES,
+            MAIN_PASS, OIT_ACCUMULATE, OIT_COMPOSITE, VIEW_DEPTH, VIEW_TARGET,
+        },
+        render_texture::{RenderTexture, RenderTextureId},
+        shader::{ShaderLibrary, ShaderSource, SHADER_DIR},
+        skinning::{self, DeformBounds, Skinning},
+        texture,
+        vertex::Vertex,
+    },
+    window::PresentMode,
+};
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+use wgpu::util::DeviceExt;
+use winit::window::{Window, WindowId};
+
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    iter, mem,
+};
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshId(pub u32);
+
+const MAIN_SHADER: &str = "shader.wgsl";
+
+// Range of a mesh inside the shared vertex, index and instance buffers.
+struct Mesh {
+    first_index: u32,
+    index_count: u32,
+    base_vertex: i32,
+    instance: u32,
 
-use bevy_math::Mat4;
-use wgpu::{util::DeviceExt, BindGroupLayout};
-use winit::window::Window;
+    // Space reserved in the shared buffers, reused when the mesh is replaced by
+    // one that fits.
+    vertex_capacity: u32,
+    index_capacity: u32,
 
-use std::iter;
+    transform: Mat4,
 
-struct Mesh {
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    index_count: u32,
+    // Sampled instead of the default texture.
+    texture: Option<RenderTextureId>,
+    // Drawn instead of the default material.
+    material: Option<MaterialId>,
+    deform: Option<Deform>,
+
+    // Local space bounds, of the current pose for deformed meshes.
+    aabb: Aabb,
+    sphere: BoundingSphere,
+}
 
-    instance_buffer: wgpu::Buffer,
+// Skinned or morphed mesh.
+struct Deform {
+    // Index in `Skinning`.
+    index: u32,
+    bounds: DeformBounds,
+}
+
+impl Mesh {
+    fn is_visible(&self, frustum: &Frustum) -> bool {
+        // Cheap sphere test first, the box is tighter for elongated meshes.
+        frustum.intersects_sphere(&self.sphere.transform(&self.transform))
+            && frustum.intersects_aabb(&self.aabb.transform(&self.transform))
+    }
 }
 
 #[repr(C)]
@@ -27,6 +99,8 @@ struct Model {
 }
 
 impl Model {
+    const SIZE: wgpu::BufferAddress = std::mem::size_of::<Model>() as wgpu::BufferAddress;
+
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
         wgpu::VertexBufferLayout {
@@ -58,34 +132,172 @@ impl Model {
     }
 }
 
-pub struct Renderer {
+// Camera copied from the world during render extraction.
+#[derive(Clone, Debug)]
+pub struct ExtractedView {
+    pub entity: Entity,
+    pub camera: Camera,
+    pub target: RenderTarget,
+    pub viewport: Viewport,
+    pub transparency: Transparency,
+}
+
+// Meshes drawn by a pass. Views with weighted blended transparency draw the
+// meshes supporting it in their own pass instead of the main one.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MeshFilter {
+    All,
+    ExceptOit,
+    Oit,
+}
+
+// Render target of a view with the primary window resolved.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Target {
+    Window(WindowId),
+    Texture(RenderTextureId),
+}
+
+// Surface and depth buffer of a window, all windows share the renderer's device.
+struct WindowSurface {
     surface: wgpu::Surface,
+    config: wgpu::SurfaceConfiguration,
+    present_modes: Vec<wgpu::PresentMode>,
+
+    depth_texture: texture::Texture,
+
+    // Unsafe reference; Declared last to not be dropped before surface.
+    window: Window,
+}
+
+impl WindowSurface {
+    // Uses the preferred sRGB format of the surface unless `format` is given.
+    fn new(
+        surface: wgpu::Surface,
+        window: Window,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: Option<wgpu::TextureFormat>,
+        present_mode: PresentMode,
+    ) -> Self {
+        let size = window.inner_size();
+        let surface_caps = surface.get_capabilities(adapter);
+
+        let surface_format = format.unwrap_or_else(|| {
+            surface_caps
+                .formats
+                .iter()
+                .copied()
+                .find(|f| f.is_srgb())
+                .unwrap_or(surface_caps.formats[0])
+        });
+
+        assert!(
+            surface_caps.formats.contains(&surface_format),
+            "Window surface doesn't support {:?}",
+            surface_format
+        );
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: present_mode.select(&surface_caps.present_modes),
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+
+        surface.configure(device, &config);
+
+        let depth_texture =
+            texture::Texture::create_depth_texture(device, &config, "depth_texture");
+
+        Self {
+            surface,
+            config,
+            present_modes: surface_caps.present_modes,
+            depth_texture,
+            window,
+        }
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(device, &self.config);
+
+            self.depth_texture =
+                texture::Texture::create_depth_texture(device, &self.config, "depth_texture");
+        }
+    }
+
+    fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        winit::dpi::PhysicalSize::new(self.config.width, self.config.height)
+    }
+}
+
+pub struct Renderer {
+    // Kept to create surfaces for new windows.
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
 
-    config: wgpu::SurfaceConfiguration,
-    size: winit::dpi::PhysicalSize<u32>,
+    // Shared by every window so pipelines work with all of them.
+    format: wgpu::TextureFormat,
+
+    primary_window: WindowId,
+    windows: HashMap<WindowId, WindowSurface>,
 
     uniform_buffer: wgpu::Buffer,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
     uniform_bind_group: wgpu::BindGroup,
 
-    depth_texture: texture::Texture,
+    // Clears the depth of a viewport when it isn't the first view of a window.
+    depth_clear_pipeline: wgpu::RenderPipeline,
 
     // Meshes
     meshes: Vec<Mesh>,
+    vertex_buffer: MegaBuffer<Vertex>,
+    index_buffer: MegaBuffer<u32>,
+    instance_buffer: MegaBuffer<Model>,
+
+    // None when the adapter can't run compute shaders, meshes are culled on the CPU instead.
+    culling: Option<GpuCulling>,
+    skinning: Skinning,
 
     // Material
+    texture: texture::Texture,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
     texture_bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
     pipeline: wgpu::RenderPipeline,
 
-    // Unsafe reference; Declared last to not be dropped before surface.
-    window: Window,
+    shaders: ShaderLibrary,
+    materials: Materials,
+
+    render_textures: Vec<RenderTexture>,
+    // Image assets sampled by materials, uploaded when loaded or reloaded.
+    images: HashMap<HandleId, texture::Texture>,
+
+    id_buffer: IdBuffer,
+    line_renderer: LineRenderer,
+
+    view_mode: ViewMode,
+    debug_view: DebugViewRenderer,
+
+    oit: OitCompositor,
+
+    // Copied from the world during render extraction, sorted by camera order.
+    views: Vec<ExtractedView>,
+
+    graph: RenderGraph,
 }
 
 impl Renderer {
-    pub async fn new(window: Window) -> Self {
-        let size = window.inner_size();
-
+    pub async fn new(window: Window, present_mode: PresentMode) -> Self {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
@@ -106,7 +318,8 @@ impl Renderer {
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    features: wgpu::Features::empty(),
+                    features: adapter.features()
+                        & (culling::optional_features() | wgpu::Features::POLYGON_MODE_LINE),
                     limits: if cfg!(target_arch = "wasm32") {
                         wgpu::Limits::downlevel_webgl2_defaults()
                     } else {
@@ -118,26 +331,9 @@ impl Renderer {
             .await
             .unwrap();
 
-        let surface_caps = surface.get_capabilities(&adapter);
-
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-        };
-
-        surface.configure(&device, &config);
+        let primary_window = window.id();
+        let window = WindowSurface::new(surface, window, &adapter, &device, None, present_mode);
+        let format = window.config.format;
 
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("uniform_buffer"),
@@ -177,9 +373,6 @@ impl Renderer {
         )
         .unwrap();
 
-        let depth_texture =
-            texture::Texture::create_depth_texture(&device, &config, "depth_texture");
-
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -218,241 +411,1527 @@ impl Renderer {
             label: Some("diffuse_bind_group"),
         });
 
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/shader.wgsl"));
-
-        let vertex = wgpu::VertexState {
-            module: &shader,
-            entry_point: "vertex",
-            buffers: &[Vertex::desc(), Model::desc()],
-        };
-
-        let fragment = wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fragment",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: config.format,
-                blend: Some(wgpu::BlendState::REPLACE),
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        };
-
         let pipeline_layout = create_pipeline_layout(
             &device,
             Some("main_pipeline_layout"),
             &[&uniform_bind_group_layout, &texture_bind_group_layout],
         );
 
-        let pipeline = create_pipeline(
+        let mut shaders = ShaderLibrary::new(SHADER_DIR);
+        let main_shader = ShaderSource::Path(MAIN_SHADER.into());
+
+        // A broken shader on disk falls back to the embedded one.
+        let pipeline = shaders
+            .process(&main_shader, &ShaderDefs::default())
+            .map_err(|err| err.to_string())
+            .and_then(|source| {
+                validated(&device, || {
+                    create_main_pipeline(&device, format, &pipeline_layout, &source)
+                })
+                .map_err(|err| err.to_string())
+            })
+            .unwrap_or_else(|err| {
+                log::error!("Failed to compile {}: {}", MAIN_SHADER, err);
+                let source = shaders
+                    .process_embedded(MAIN_SHADER, &ShaderDefs::default())
+                    .unwrap();
+                create_main_pipeline(&device, format, &pipeline_layout, &source)
+            });
+
+        let id_shader = device.create_shader_module(wgpu::include_wgsl!("shaders/id.wgsl"));
+
+        let id_pipeline_layout = create_pipeline_layout(
+            &device,
+            Some("id_pipeline_layout"),
+            &[&uniform_bind_group_layout],
+        );
+
+        let id_pipeline = create_pipeline(
+            &device,
+            Some("id_pipeline"),
+            wgpu::VertexState {
+                module: &id_shader,
+                entry_point: "vertex",
+                buffers: &[Vertex::desc(), Model::desc()],
+            },
+            wgpu::FragmentState {
+                module: &id_shader,
+                entry_point: "fragment",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: id_buffer::ID_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            },
+            &id_pipeline_layout,
+            &RenderState::default(),
+        );
+
+        let id_buffer = IdBuffer::new(&device, &uniform_bind_group_layout, id_pipeline);
+
+        let depth_clear_shader =
+            device.create_shader_module(wgpu::include_wgsl!("shaders/clear_depth.wgsl"));
+
+        let depth_clear_pipeline = create_pipeline(
+            &device,
+            Some("depth_clear_pipeline"),
+            wgpu::VertexState {
+                module: &depth_clear_shader,
+                entry_point: "vertex",
+                buffers: &[],
+            },
+            wgpu::FragmentState {
+                module: &depth_clear_shader,
+                entry_point: "fragment",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::empty(),
+                })],
+            },
+            &create_pipeline_layout(&device, Some("depth_clear_pipeline_layout"), &[]),
+            &RenderState {
+                cull_mode: None,
+                depth_compare: wgpu::CompareFunction::Always,
+                ..Default::default()
+            },
+        );
+
+        let line_renderer = LineRenderer::new(&device, format, &uniform_bind_group_layout);
+
+        let debug_view = DebugViewRenderer::new(
+            &device,
+            format,
+            &uniform_bind_group_layout,
+            Vertex::desc(),
+            Model::desc(),
+        );
+
+        let oit = OitCompositor::new(&device, format);
+
+        let skinning = Skinning::new(&device, skinning::is_supported(&adapter));
+
+        let vertex_buffer = MegaBuffer::new(
+            &device,
+            "vertex_buffer",
+            wgpu::BufferUsages::VERTEX | skinning.vertex_usage(),
+        );
+        let index_buffer = MegaBuffer::new(&device, "index_buffer", wgpu::BufferUsages::INDEX);
+        let instance_buffer = MegaBuffer::new(
             &device,
-            Some("main_pipeline"),
-            vertex,
-            fragment,
-            &pipeline_layout,
+            "instance_buffer",
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::STORAGE,
         );
 
+        let culling = if culling::is_supported(&adapter) {
+            Some(GpuCulling::new(&device))
+        } else {
+            log::info!("Compute shaders unsupported, falling back to CPU culling");
+            None
+        };
+
         Self {
-            surface,
+            instance,
+            adapter,
             device,
             queue,
-            config,
-            size,
+            format,
+            primary_window,
+            windows: HashMap::from([(primary_window, window)]),
             uniform_buffer,
+            uniform_bind_group_layout,
             uniform_bind_group,
-            depth_texture,
+            depth_clear_pipeline,
             meshes: vec![],
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            culling,
+            skinning,
+            texture,
+            texture_bind_group_layout,
             texture_bind_group,
+            pipeline_layout,
             pipeline,
-            window,
+            shaders,
+            materials: Materials::default(),
+            render_textures: vec![],
+            images: HashMap::new(),
+            id_buffer,
+            line_renderer,
+            view_mode: ViewMode::default(),
+            debug_view,
+            oit,
+            views: vec![],
+            graph: default_graph(),
         }
     }
 
-    pub fn create_mesh(&mut self, vertices: Vec<Vertex>, indices: Vec<u32>, transform: &Mat4) {
-        let vertex_buffer = self
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("vertex_buffer"),
-                contents: bytemuck::cast_slice(vertices.as_slice()),
-                usage: wgpu::BufferUsages::VERTEX,
-            });
+    pub fn create_mesh(&mut self, data: &MeshData, transform: &Mat4) -> MeshId {
+        let base_vertex = self.vertex_buffer.push(&data.vertices) as i32;
+        let first_index = self.index_buffer.push(&data.indices);
+        let index_count = data.indices.len() as u32;
 
-        let index_buffer = self
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("index_buffer"),
-                contents: bytemuck::cast_slice(indices.as_slice()),
-                usage: wgpu::BufferUsages::INDEX,
-            });
+        let instance = self.instance_buffer.push(&[Model {
+            data: transform.to_cols_array_2d(),
+        }]);
 
-        let index_count = indices.len() as u32;
+        let deform = data.is_deformed().then(|| Deform {
+            index: self.skinning.add(data, base_vertex),
+            bounds: DeformBounds::new(data),
+        });
 
-        let instance_buffer = self
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("instance_buffer"),
-                contents: bytemuck::cast_slice(&vec![transform.to_cols_array_2d()]),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        if let Some(culling) = &mut self.culling {
+            culling.push_draw(DrawData {
+                sphere: draw_sphere(&data.sphere),
+                index_count,
+                first_index,
+                base_vertex,
+                instance,
             });
+        }
 
         let mesh = Mesh {
-            vertex_buffer,
-            index_buffer,
+            first_index,
             index_count,
+            base_vertex,
+            instance,
 
-            instance_buffer,
+            vertex_capacity: data.vertices.len() as u32,
+            index_capacity: index_count,
+
+            transform: *transform,
+
+            texture: None,
+            material: None,
+            deform,
+
+            aabb: data.aabb,
+            sphere: data.sphere,
         };
 
         self.meshes.push(mesh);
+
+        MeshId(self.meshes.len() as u32 - 1)
     }
 
-    fn update_uniform_buffer(&mut self, camera: &Camera) {
-        let aspect = self.config.width as f32 / self.config.height as f32;
+    // Replaces the geometry of a mesh, e.g. when its asset is reloaded. The new
+    // one is written over the old one when it fits and appended otherwise.
+    pub fn update_mesh(&mut self, id: MeshId, data: &MeshData) {
+        let mesh = match self.meshes.get_mut(id.0 as usize) {
+            Some(mesh) => mesh,
+            None => return,
+        };
 
-        let ubo = UniformBufferObject {
-            view_proj: camera.get_view_projection_matrix(aspect).to_cols_array_2d(),
+        let vertex_count = data.vertices.len() as u32;
+        let index_count = data.indices.len() as u32;
+
+        if vertex_count <= mesh.vertex_capacity && index_count <= mesh.index_capacity {
+            self.vertex_buffer
+                .write(mesh.base_vertex as usize, &data.vertices);
+            self.index_buffer
+                .write(mesh.first_index as usize, &data.indices);
+        } else {
+            // The old ranges are left unused.
+            mesh.base_vertex = self.vertex_buffer.push(&data.vertices) as i32;
+            mesh.first_index = self.index_buffer.push(&data.indices);
+            mesh.vertex_capacity = vertex_count;
+            mesh.index_capacity = index_count;
+        }
+
+        mesh.index_count = index_count;
+        mesh.aabb = data.aabb;
+        mesh.sphere = data.sphere;
+
+        mesh.deform = match (mesh.deform.take(), data.is_deformed()) {
+            (Some(mut deform), true) => {
+                self.skinning.update(deform.index, data, mesh.base_vertex);
+                deform.bounds.set_mesh(data);
+                Some(deform)
+            }
+            (Some(deform), false) => {
+                self.skinning.remove(deform.index);
+                None
+            }
+            (None, true) => Some(Deform {
+                index: self.skinning.add(data, mesh.base_vertex),
+                bounds: DeformBounds::new(data),
+            }),
+            (None, false) => None,
         };
 
-        self.queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[ubo]));
+        if let Some(deform) = &mesh.deform {
+            mesh.aabb = deform.bounds.aabb();
+            mesh.sphere = aabb_sphere(&mesh.aabb);
+        }
+
+        // Draws are pushed along with meshes, so they share indices.
+        if let Some(culling) = &mut self.culling {
+            culling.set_draw(
+                id.0,
+                DrawData {
+                    sphere: draw_sphere(&mesh.sphere),
+                    index_count,
+                    first_index: mesh.first_index,
+                    base_vertex: mesh.base_vertex,
+                    instance: mesh.instance,
+                },
+            );
+        }
+    }
+
+    // Stops drawing a mesh. Its ranges in the shared buffers are left unused and
+    // its id isn't reused.
+    pub fn remove_mesh(&mut self, id: MeshId) {
+        let mesh = match self.meshes.get_mut(id.0 as usize) {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        mesh.index_count = 0;
+        mesh.texture = None;
+        mesh.material = None;
+
+        if let Some(deform) = mesh.deform.take() {
+            self.skinning.remove(deform.index);
+        }
+
+        if let Some(culling) = &mut self.culling {
+            culling.set_draw(
+                id.0,
+                DrawData {
+                    sphere: [0.0; 4],
+                    index_count: 0,
+                    first_index: mesh.first_index,
+                    base_vertex: mesh.base_vertex,
+                    instance: mesh.instance,
+                },
+            );
+        }
+    }
+
+    // Moves a mesh, its instance is uploaded with the next frame.
+    pub fn set_mesh_transform(&mut self, id: MeshId, transform: &Mat4) {
+        let mesh = match self.meshes.get_mut(id.0 as usize) {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        mesh.transform = *transform;
+        self.instance_buffer.set(
+            mesh.instance as usize,
+            Model {
+                data: transform.to_cols_array_2d(),
+            },
+        );
+    }
+
+    // Poses skinned meshes with the joints of the last `set_mesh_joints`.
+    pub fn set_mesh_joints(&mut self, id: MeshId, joints: &[Mat4]) {
+        let deform = match self.deform_mut(id) {
+            Some(deform) => deform,
+            None => return,
+        };
+
+        deform.bounds.set_joints(joints);
+        let index = deform.index;
+        self.skinning.set_joints(index, joints);
+        self.update_deformed_bounds(id);
+    }
+
+    // Weights of the mesh's morph targets, applied before the skin.
+    pub fn set_mesh_morph_weights(&mut self, id: MeshId, weights: &[f32]) {
+        let deform = match self.deform_mut(id) {
+            Some(deform) => deform,
+            None => return,
+        };
+
+        deform.bounds.set_morph_weights(weights);
+        let index = deform.index;
+        self.skinning.set_morph_weights(index, weights);
+        self.update_deformed_bounds(id);
+    }
+
+    fn deform_mut(&mut self, id: MeshId) -> Option<&mut Deform> {
+        self.meshes
+            .get_mut(id.0 as usize)
+            .and_then(|mesh| mesh.deform.as_mut())
+    }
+
+    // Culls and sorts a deformed mesh by the bounds of its current pose.
+    fn update_deformed_bounds(&mut self, id: MeshId) {
+        let mesh = &mut self.meshes[id.0 as usize];
+        let deform = match &mesh.deform {
+            Some(deform) => deform,
+            None => return,
+        };
+
+        mesh.aabb = deform.bounds.aabb();
+        mesh.sphere = aabb_sphere(&mesh.aabb);
+
+        if let Some(culling) = &mut self.culling {
+            culling.set_draw(
+                id.0,
+                DrawData {
+                    sphere: draw_sphere(&mesh.sphere),
+                    index_count: mesh.index_count,
+                    first_index: mesh.first_index,
+                    base_vertex: mesh.base_vertex,
+                    instance: mesh.instance,
+                },
+            );
+        }
+    }
+
+    fn upload_vertices(&mut self) {
+        let reallocated = self.vertex_buffer.upload(&self.device, &self.queue);
+        self.skinning.prepare(
+            &self.device,
+            &self.queue,
+            self.vertex_buffer.buffer(),
+            reallocated,
+        );
     }
 
-    pub fn render(&mut self, camera: Option<&Camera>) -> Result<(), wgpu::SurfaceError> {
-        match camera {
-            Some(x) => self.update_uniform_buffer(x),
-            None => (),
+    // Runs once per frame before any view, the skinned vertices are then left
+    // alone until the next frame.
+    fn skin_meshes(&mut self) {
+        if self.skinning.is_empty() {
+            return;
         }
 
-        let output = self.surface.get_current_texture()?;
+        self.skinning.skin_on_cpu(&mut self.vertex_buffer);
+        self.upload_vertices();
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
+                label: Some("Skin Encoder"),
             });
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.skinning.dispatch(&mut encoder);
+        self.queue.submit(iter::once(encoder.finish()));
+    }
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[
-                // This is what @location(0) in the fragment shader targets
-                Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                }),
-            ],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            ..Default::default()
-        });
+    fn upload_meshes(&mut self, frustum: Option<&Frustum>) {
+        self.upload_vertices();
+        self.index_buffer.upload(&self.device, &self.queue);
+        let instances_reallocated = self.instance_buffer.upload(&self.device, &self.queue);
+
+        if let Some(culling) = &mut self.culling {
+            culling.prepare(
+                &self.device,
+                &self.queue,
+                self.instance_buffer.buffer(),
+                instances_reallocated,
+                frustum,
+            );
+        }
+    }
+
+    fn update_uniform_buffer(&self, camera: &Camera, aspect: f32) {
+        let ubo = UniformBufferObject {
+            view_proj: camera.get_view_projection_matrix(aspect).to_cols_array_2d(),
+        };
 
-        render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-        render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[ubo]));
 
-        for mesh in self.meshes.iter() {
-            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-            render_pass.set_vertex_buffer(1, mesh.instance_buffer.slice(..));
-            render_pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.debug_view.update_uniform_buffer(&self.queue, camera);
+    }
 
-            render_pass.draw_indexed(0..mesh.index_count, 0, 0..1 as _);
-        }
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
 
-        // RenderPass needs to be dropped in order to submit to queue.
-        drop(render_pass);
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
 
-        self.queue.submit(iter::once(encoder.finish()));
-        output.present();
+    // Color format of every window and render texture.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
 
-        Ok(())
+    // Passes drawing each view, custom nodes can be added around the built-in ones.
+    pub fn graph_mut(&mut self) -> &mut RenderGraph {
+        &mut self.graph
     }
 
-    pub fn resize_surface(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+    pub fn set_view_mode(&mut self, view_mode: ViewMode) {
+        self.view_mode = view_mode;
+    }
 
-            self.depth_texture =
-                texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+    pub fn set_present_mode(&mut self, window_id: WindowId, present_mode: PresentMode) {
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            window.config.present_mode = present_mode.select(&window.present_modes);
+            window.surface.configure(&self.device, &window.config);
         }
     }
 
-    pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> {
-        return self.size;
+    // Views are drawn in order, later views on top of earlier ones.
+    pub fn set_views(&mut self, views: Vec<ExtractedView>) {
+        self.views = views;
     }
 
-    pub fn get_window(&self) -> &Window {
-        return &self.window;
+    // Creates an offscreen texture cameras can draw to with `RenderTarget::Texture`
+    // and meshes can sample with `MaterialTexture`. It uses the window format.
+    pub fn create_render_texture(&mut self, width: u32, height: u32) -> RenderTextureId {
+        self.render_textures.push(RenderTexture::new(
+            &self.device,
+            &self.texture_bind_group_layout,
+            self.format,
+            UVec2::new(width, height),
+        ));
+
+        RenderTextureId(self.render_textures.len() as u32 - 1)
     }
-}
 
-fn create_pipeline_layout(
-    device: &wgpu::Device,
-    label: Option<&str>,
-    bind_group_layouts: &[&BindGroupLayout],
-) -> wgpu::PipelineLayout {
-    let descriptor = wgpu::PipelineLayoutDescriptor {
-        label,
-        bind_group_layouts,
-        push_constant_ranges: &[],
-    };
-
-    return device.create_pipeline_layout(&descriptor);
-}
+    // Does nothing when the size doesn't change, so it can be called every frame.
+    pub fn resize_render_texture(&mut self, texture: RenderTextureId, width: u32, height: u32) {
+        // Render textures are at least one pixel wide and high.
+        let size = UVec2::new(width, height).max(UVec2::ONE);
 
-fn create_pipeline(
-    device: &wgpu::Device,
-    label: Option<&str>,
-    vertex: wgpu::VertexState,
-    fragment: wgpu::FragmentState,
-    pipeline_layout: &wgpu::PipelineLayout,
-) -> wgpu::RenderPipeline {
-    let descriptor = wgpu::RenderPipelineDescriptor {
-        label,
-        layout: Some(pipeline_layout),
-        vertex,
-        fragment: Some(fragment),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            polygon_mode: wgpu::PolygonMode::Fill,
-            unclipped_depth: false,
-            conservative: false,
-        },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: texture::DEPTH_FORMAT,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState {
-            count: 1,
-            mask: !0,
-            alpha_to_coverage_enabled: false,
-        },
-        multiview: None,
-    };
+        let render_texture = match self.render_textures.get_mut(texture.0 as usize) {
+            Some(render_texture) if render_texture.size() != size => render_texture,
+            _ => return,
+        };
+
+        *render_texture = RenderTexture::new(
+            &self.device,
+            &self.texture_bind_group_layout,
+            self.format,
+            size,
+        );
+
+        let mut materials = mem::take(&mut self.materials);
+        materials.recreate_bind_groups(&self.material_context(&Self::vertex_layouts()), |source| {
+            *source == TextureSource::RenderTexture(texture)
+        });
+        self.materials = materials;
+    }
+
+    // Uploads an image asset for the materials sampling it, replacing the
+    // previous upload when the asset is reloaded.
+    pub fn set_image(&mut self, handle: Handle<image::DynamicImage>, image: &image::DynamicImage) {
+        let texture = match texture::Texture::from_image(
+            &self.device,
+            &self.queue,
+            image,
+            Some("image_asset"),
+        ) {
+            Ok(texture) => texture,
+            Err(err) => return log::error!("Failed to upload {:?}: {}", handle, err),
+        };
+
+        self.images.insert(handle.id, texture);
+
+        let mut materials = mem::take(&mut self.materials);
+        materials.recreate_bind_groups(
+            &self.material_context(&Self::vertex_layouts()),
+            |source| matches!(source, TextureSource::Asset(asset) if asset.id == handle.id),
+        );
+        self.materials = materials;
+    }
+
+    pub fn render_texture_size(&self, texture: RenderTextureId) -> Option<UVec2> {
+        self.render_textures
+            .get(texture.0 as usize)
+            .map(|render_texture| render_texture.size())
+    }
+
+    // Builds the pipeline of a custom material, or reuses a cached one.
+    pub fn add_material(&mut self, material: &impl CustomMaterial) -> MaterialId {
+        let source = self.load_shader(&material.shader(), &material.defs());
+
+        let mut materials = mem::take(&mut self.materials);
+        let id = materials.add(
+            &self.material_context(&Self::vertex_layouts()),
+            material,
+            source,
+        );
+        self.materials = materials;
+
+        id
+    }
+
+    // Uploads the material's bindings again, rebuilding its pipeline if the
+    // shader or state changed.
+    pub fn update_material(&mut self, id: MaterialId, material: &impl CustomMaterial) {
+        let source = self.load_shader(&material.shader(), &material.defs());
+
+        let mut materials = mem::take(&mut self.materials);
+        materials.update(
+            &self.material_context(&Self::vertex_layouts()),
+            id,
+            material,
+            source,
+        );
+        self.materials = materials;
+    }
+
+    fn load_shader(&mut self, shader: &ShaderSource, defs: &ShaderDefs) -> Option<String> {
+        self.shaders
+            .process(shader, defs)
+            .map_err(|err| log::error!("Failed to load shader {:?}: {}", shader, err))
+            .ok()
+    }
+
+    // Rebuilds the pipelines whose shader files, or files they include, changed
+    // on disk. A shader that doesn't compile is logged and its previous pipeline
+    // kept.
+    pub fn reload_shaders(&mut self) {
+        if self.shaders.reload_changed().is_empty() {
+            return;
+        }
+
+        let main_shader = ShaderSource::Path(MAIN_SHADER.into());
+        if let Some(source) = self.load_shader(&main_shader, &ShaderDefs::default()) {
+            let pipeline = validated(&self.device, || {
+                create_main_pipeline(&self.device, self.format, &self.pipeline_layout, &source)
+            });
+
+            match pipeline {
+                Ok(pipeline) => self.pipeline = pipeline,
+                Err(err) => log::error!("Failed to compile {}: {}", MAIN_SHADER, err),
+            }
+        }
+
+        let mut materials = mem::take(&mut self.materials);
+        let sources = materials
+            .variants()
+            .map(|(shader, defs)| self.load_shader(shader, defs))
+            .collect();
+
+        materials.reload(&self.material_context(&Self::vertex_layouts()), sources);
+        self.materials = materials;
+    }
+
+    // None goes back to the default material.
+    pub fn set_mesh_material(&mut self, mesh: MeshId, material: Option<MaterialId>) {
+        if let Some(mesh) = self.meshes.get_mut(mesh.0 as usize) {
+            mesh.material = material;
+        }
+    }
+
+    fn vertex_layouts() -> [wgpu::VertexBufferLayout<'static>; 2] {
+        [Vertex::desc(), Model::desc()]
+    }
+
+    fn material_context<'a>(
+        &'a self,
+        vertex_layouts: &'a [wgpu::VertexBufferLayout<'a>],
+    ) -> MaterialContext<'a> {
+        MaterialContext {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.format,
+            camera_layout: &self.uniform_bind_group_layout,
+            vertex_layouts,
+            default_texture: &self.texture,
+            render_textures: &self.render_textures,
+            images: &self.images,
+        }
+    }
+
+    // None goes back to the default texture.
+    pub fn set_mesh_texture(&mut self, mesh: MeshId, texture: Option<RenderTextureId>) {
+        if let Some(mesh) = self.meshes.get_mut(mesh.0 as usize) {
+            mesh.texture = texture;
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.skin_meshes();
+
+        if self
+            .debug_view
+            .barycentric_pipeline(self.view_mode)
+            .is_some()
+        {
+            self.debug_view.prepare_barycentric(
+                &self.device,
+                &self.queue,
+                self.vertex_buffer.as_slice(),
+                self.index_buffer.as_slice(),
+                self.meshes
+                    .iter()
+                    .map(|mesh| (mesh.first_index, mesh.index_count, mesh.base_vertex)),
+            );
+        }
+
+        let mut frames = vec![];
+        let mut window_views = HashMap::new();
+
+        for (window_id, window) in &mut self.windows {
+            match window.surface.get_current_texture() {
+                Ok(output) => {
+                    let view = output
+                        .texture
+                        .create_view(&wgpu::TextureViewDescriptor::default());
+
+                    window_views.insert(*window_id, view);
+                    frames.push((*window_id, output));
+                }
+                // Reconfigure the surface if it's lost or outdated
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    window.resize(&self.device, window.size());
+                }
+                Err(wgpu::SurfaceError::Timeout) => log::warn!("Surface timeout"),
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Views run in camera order across all targets, so textures are drawn
+        // before the views sampling them. The first view of a target clears it.
+        let mut cleared = HashSet::new();
+
+        for index in 0..self.views.len() {
+            let target = match self.resolve_target(self.views[index].target) {
+                Some(target) => target,
+                None => continue,
+            };
+
+            if let Target::Window(window_id) = target {
+                if !window_views.contains_key(&window_id) {
+                    continue;
+                }
+            }
+
+            let first = cleared.insert(target);
+            self.render_view(index, target, &window_views, first);
+        }
+
+        self.graph.end_frame();
+
+        for (window_id, output) in frames {
+            if !cleared.contains(&Target::Window(window_id)) {
+                self.clear_window(window_id, &window_views[&window_id]);
+            }
+
+            output.present();
+        }
+
+        Ok(())
+    }
+
+    fn clear_window(&self, window_id: WindowId, view: &wgpu::TextureView) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Clear Encoder"),
+            });
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Clear Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.windows[&window_id].depth_texture.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        self.queue.submit(iter::once(encoder.finish()));
+    }
+
+    // Views are submitted one at a time since they share the camera uniforms and
+    // the culled draw commands.
+    fn render_view(
+        &mut self,
+        index: usize,
+        target: Target,
+        window_views: &HashMap<WindowId, wgpu::TextureView>,
+        first: bool,
+    ) {
+        let ExtractedView {
+            camera,
+            viewport,
+            transparency,
+            ..
+        } = self.views[index].clone();
+
+        let size = match self.target_size(target) {
+            Some(size) => size,
+            None => return,
+        };
+        let (offset, extent) = viewport.to_pixels(size);
+        let aspect = extent.x as f32 / extent.y as f32;
+
+        self.update_uniform_buffer(&camera, aspect);
+        self.upload_meshes(Some(&camera.get_frustum(aspect)));
+
+        let view = ViewInfo {
+            camera,
+            offset,
+            extent,
+            size,
+            first,
+            texture: match target {
+                Target::Window(_) => None,
+                Target::Texture(texture) => Some(texture),
+            },
+            transparency,
+        };
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        // The graph is taken out while it runs so its nodes can borrow the renderer.
+        let mut graph = mem::take(&mut self.graph);
+
+        let (color_view, depth_view) = match target {
+            Target::Window(window_id) => (
+                &window_views[&window_id],
+                &self.windows[&window_id].depth_texture.view,
+            ),
+            Target::Texture(texture) => {
+                let render_texture = &self.render_textures[texture.0 as usize];
+                (&render_texture.color.view, &render_texture.depth.view)
+            }
+        };
+
+        graph.run(self, &view, color_view, depth_view, &mut encoder);
+
+        self.graph = graph;
+        self.queue.submit(iter::once(encoder.finish()));
+    }
+
+    fn draw_meshes<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        frustum: Option<&Frustum>,
+        view_matrix: &Mat4,
+        target_texture: Option<RenderTextureId>,
+        filter: MeshFilter,
+    ) {
+        let debug_pipeline = self.debug_view.pipeline(self.view_mode);
+        let oit = filter == MeshFilter::Oit;
+
+        // Meshes with a custom material or sampling a render texture bind their
+        // own pipeline and textures, so they can't be part of a single multi draw.
+        let per_mesh = oit
+            || debug_pipeline.is_none()
+                && self
+                    .meshes
+                    .iter()
+                    .any(|mesh| mesh.texture.is_some() || mesh.material.is_some());
+
+        // The accumulation targets don't match the shared pipelines, every
+        // mesh drawn into them binds its material.
+        match debug_pipeline {
+            _ if oit => {}
+            Some(pipeline) => {
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(1, &self.debug_view.uniform_bind_group, &[]);
+            }
+            None => {
+                render_pass.set_pipeline(&self.pipeline);
+                render_pass.set_bind_group(1, &self.texture_bind_group, &[]);
+            }
+        }
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer().slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(..));
+        render_pass.set_index_buffer(
+            self.index_buffer.buffer().slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+
+        match &self.culling {
+            Some(culling) if culling.supports_multi_draw() && !per_mesh => {
+                render_pass.multi_draw_indexed_indirect(
+                    culling.indirect_buffer(),
+                    0,
+                    culling.draw_count(),
+                );
+            }
+            Some(culling) => {
+                for i in self.draw_order(view_matrix, filter) {
+                    let mesh = &self.meshes[i];
+
+                    if per_mesh && !self.bind_material(render_pass, mesh, target_texture, oit) {
+                        continue;
+                    }
+
+                    // Without first_instance support every command starts at instance 0.
+                    if !culling.supports_first_instance() {
+                        let offset = mesh.instance as wgpu::BufferAddress * Model::SIZE;
+                        render_pass
+                            .set_vertex_buffer(1, self.instance_buffer.buffer().slice(offset..));
+                    }
+
+                    render_pass.draw_indexed_indirect(
+                        culling.indirect_buffer(),
+                        i as wgpu::BufferAddress * INDIRECT_STRIDE,
+                    );
+                }
+            }
+            None => {
+                let visible_meshes = self
+                    .draw_order(view_matrix, filter)
+                    .into_iter()
+                    .map(|i| &self.meshes[i])
+                    .filter(|mesh| match frustum {
+                        Some(frustum) => mesh.is_visible(frustum),
+                        None => true,
+                    });
+
+                for mesh in visible_meshes {
+                    if per_mesh && !self.bind_material(render_pass, mesh, target_texture, oit) {
+                        continue;
+                    }
+
+                    render_pass.draw_indexed(
+                        mesh.first_index..mesh.first_index + mesh.index_count,
+                        mesh.base_vertex,
+                        mesh.instance..mesh.instance + 1,
+                    );
+                }
+            }
+        }
+    }
+
+    // Indices of the meshes queue by queue. Transparent meshes are sorted by the
+    // view space depth of their center, farthest first; the camera looks down -Z.
+    fn draw_order(&self, view_matrix: &Mat4, filter: MeshFilter) -> Vec<usize> {
+        let queue = |mesh: &Mesh| match mesh.material {
+            Some(material) => self.materials.queue(material),
+            None => RenderQueue::Opaque,
+        };
+        let depth = |mesh: &Mesh| {
+            view_matrix
+                .transform_point3(mesh.transform.transform_point3(mesh.sphere.center))
+                .z
+        };
+
+        let mut order: Vec<_> = (0..self.meshes.len())
+            .filter(|i| match filter {
+                MeshFilter::All => true,
+                MeshFilter::ExceptOit => !self.has_oit(&self.meshes[*i]),
+                MeshFilter::Oit => self.has_oit(&self.meshes[*i]),
+            })
+            .collect();
+
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.meshes[*a], &self.meshes[*b]);
+
+            queue(a).cmp(&queue(b)).then_with(|| match queue(a) {
+                RenderQueue::Transparent => depth(a).total_cmp(&depth(b)),
+                _ => Ordering::Equal,
+            })
+        });
+
+        order
+    }
+
+    fn has_oit(&self, mesh: &Mesh) -> bool {
+        mesh.material
+            .is_some_and(|material| self.materials.has_oit(material))
+    }
+
+    // Whether a view draws some meshes with weighted blended transparency. Debug
+    // views draw every mesh in the main pass.
+    fn draws_oit(&self, view: &ViewInfo) -> bool {
+        view.transparency == Transparency::WeightedBlended
+            && self.debug_view.pipeline(self.view_mode).is_none()
+            && self
+                .debug_view
+                .barycentric_pipeline(self.view_mode)
+                .is_none()
+            && self.meshes.iter().any(|mesh| self.has_oit(mesh))
+    }
+
+    // Binds the pipeline and textures of a mesh's material, false when the mesh
+    // can't be drawn into `target_texture`.
+    fn bind_material<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        mesh: &Mesh,
+        target_texture: Option<RenderTextureId>,
+        oit: bool,
+    ) -> bool {
+        let (pipeline, bind_group) = match mesh.material {
+            Some(material) => match self.materials.get(material, target_texture, oit) {
+                Some(material) => material,
+                None => return false,
+            },
+            None => (
+                &self.pipeline,
+                self.material_bind_group(mesh, target_texture),
+            ),
+        };
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(1, bind_group, &[]);
+
+        true
+    }
+
+    // A texture can't be sampled while it's being drawn to, so a mesh showing
+    // the target it's drawn into falls back to the default texture.
+    fn material_bind_group(
+        &self,
+        mesh: &Mesh,
+        target_texture: Option<RenderTextureId>,
+    ) -> &wgpu::BindGroup {
+        match mesh.texture {
+            Some(texture) if target_texture != Some(texture) => self
+                .render_textures
+                .get(texture.0 as usize)
+                .map_or(&self.texture_bind_group, |render_texture| {
+                    &render_texture.bind_group
+                }),
+            _ => &self.texture_bind_group,
+        }
+    }
+
+    // Copies this frame's debug lines, drawn on top of the scene by the next `render`.
+    pub fn prepare_debug_lines(&mut self, debug_draw: &DebugDraw) {
+        self.line_renderer
+            .prepare(&self.device, &self.queue, debug_draw);
+    }
+
+    // Renders mesh ids under the cursor and reads the closest one back, blocking
+    // until the GPU is done. `position` is relative to a viewport of `size` pixels.
+    pub fn pick_mesh_id(&mut self, camera: &Camera, position: Vec2, size: Vec2) -> Option<MeshId> {
+        self.upload_meshes(None);
+
+        let view_proj = camera.get_view_projection_matrix(size.x / size.y);
+        let pick_matrix = IdBuffer::pick_matrix(&view_proj, position, size);
+        let frustum = Frustum::from_view_projection(&pick_matrix);
+
+        self.id_buffer
+            .update_uniform_buffer(&self.queue, &pick_matrix);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Pick Encoder"),
+            });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Pick Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.id_buffer.id_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.id_buffer.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        render_pass.set_pipeline(&self.id_buffer.pipeline);
+        render_pass.set_bind_group(0, &self.id_buffer.uniform_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer().slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(..));
+        render_pass.set_index_buffer(
+            self.index_buffer.buffer().slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+
+        for mesh in self.meshes.iter().filter(|mesh| mesh.is_visible(&frustum)) {
+            render_pass.draw_indexed(
+                mesh.first_index..mesh.first_index + mesh.index_count,
+                mesh.base_vertex,
+                mesh.instance..mesh.instance + 1,
+            );
+        }
+
+        drop(render_pass);
+
+        self.id_buffer.copy_to_readback(&mut encoder);
+        self.queue.submit(iter::once(encoder.finish()));
+
+        let instance = self.id_buffer.read(&self.device)?.checked_sub(1)?;
+
+        self.meshes
+            .iter()
+            .position(|mesh| mesh.instance == instance)
+            .map(|index| MeshId(index as u32))
+    }
+
+    // Creates a surface for another window, drawn by cameras targeting it.
+    pub fn add_window(&mut self, window: Window, present_mode: PresentMode) -> WindowId {
+        let surface = unsafe { self.instance.create_surface(&window) }.unwrap();
+        let window_id = window.id();
+
+        let window = WindowSurface::new(
+            surface,
+            window,
+            &self.adapter,
+            &self.device,
+            Some(self.format),
+            present_mode,
+        );
+
+        self.windows.insert(window_id, window);
+        window_id
+    }
+
+    // Closes a secondary window, the primary window lives as long as the renderer.
+    pub fn remove_window(&mut self, window_id: WindowId) {
+        if window_id == self.primary_window {
+            log::warn!("The primary window can't be removed");
+            return;
+        }
+
+        self.windows.remove(&window_id);
+    }
+
+    pub fn resize_surface(&mut self, window_id: WindowId, new_size: winit::dpi::PhysicalSize<u32>) {
+        if let Some(window) = self.windows.get_mut(&window_id) {
+            window.resize(&self.device, new_size);
+        }
+    }
+
+    pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        return self.windows[&self.primary_window].size();
+    }
+
+    pub fn get_window(&self) -> &Window {
+        return &self.windows[&self.primary_window].window;
+    }
+
+    pub fn primary_window(&self) -> WindowId {
+        self.primary_window
+    }
+
+    pub fn window(&self, window_id: WindowId) -> Option<&Window> {
+        self.windows.get(&window_id).map(|window| &window.window)
+    }
+
+    pub fn window_size(&self, window_id: WindowId) -> Option<winit::dpi::PhysicalSize<u32>> {
+        self.windows.get(&window_id).map(|window| window.size())
+    }
+
+    // None for textures and closed windows.
+    pub fn target_window(&self, target: RenderTarget) -> Option<WindowId> {
+        let window_id = match target {
+            RenderTarget::PrimaryWindow => self.primary_window,
+            RenderTarget::Window(window_id) => window_id,
+            RenderTarget::Texture(_) => return None,
+        };
+
+        self.windows.contains_key(&window_id).then_some(window_id)
+    }
+
+    fn resolve_target(&self, target: RenderTarget) -> Option<Target> {
+        match target {
+            RenderTarget::Texture(texture) => self
+                .render_texture_size(texture)
+                .map(|_| Target::Texture(texture)),
+            _ => self.target_window(target).map(Target::Window),
+        }
+    }
+
+    fn target_size(&self, target: Target) -> Option<UVec2> {
+        match target {
+            Target::Window(window_id) => self
+                .window_size(window_id)
+                .map(|size| UVec2::new(size.width, size.height)),
+            Target::Texture(texture) => self.render_texture_size(texture),
+        }
+    }
+
+    // Finds the camera drawn on top under the cursor among the extracted views.
+    // Returns its entity with the cursor position relative to the viewport and
+    // the viewport size in pixels.
+    pub fn view_under_cursor(
+        &self,
+        window_id: WindowId,
+        position: Vec2,
+    ) -> Option<(Entity, Vec2, Vec2)> {
+        let size = self.window_size(window_id)?;
+
+        self.views
+            .iter()
+            .filter(|view| self.target_window(view.target) == Some(window_id))
+            .filter_map(|view| {
+                let (offset, extent) = view.viewport.to_pixels(UVec2::new(size.width, size.height));
+
+                let local = position - offset.as_vec2();
+                let inside = local.cmpge(Vec2::ZERO).all() && local.cmplt(extent.as_vec2()).all();
+
+                inside.then_some((view.entity, local, extent.as_vec2()))
+            })
+            .last()
+    }
+
+    // The renderer is stored in the world as a non-send resource. Temporarily
+    // takes it out so `f` can use it alongside the rest of the world, None when
+    // the world has no renderer.
+    pub fn scope<T>(
+        world: &mut World,
+        f: impl FnOnce(&mut World, &mut Renderer) -> T,
+    ) -> Option<T> {
+        let mut renderer = world.remove_non_send_resource::<Renderer>()?;
+        let result = f(world, &mut renderer);
+        world.insert_non_send_resource(renderer);
+
+        Some(result)
+    }
+}
+
+fn set_viewport(render_pass: &mut wgpu::RenderPass, offset: UVec2, extent: UVec2) {
+    render_pass.set_viewport(
+        offset.x as f32,
+        offset.y as f32,
+        extent.x as f32,
+        extent.y as f32,
+        0.0,
+        1.0,
+    );
+    render_pass.set_scissor_rect(offset.x, offset.y, extent.x, extent.y);
+}
+
+fn create_main_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    layout: &wgpu::PipelineLayout,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(MAIN_SHADER),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+
+    create_pipeline(
+        device,
+        Some("main_pipeline"),
+        wgpu::VertexState {
+            module: &shader,
+            entry_point: "vertex",
+            buffers: &[Vertex::desc(), Model::desc()],
+        },
+        wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fragment",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        },
+        layout,
+        &RenderState::default(),
+    )
+}
+
+fn default_graph() -> RenderGraph {
+    let mut graph = RenderGraph::default();
+
+    for (name, format) in [
+        (OIT_ACCUM, oit::ACCUM_FORMAT),
+        (OIT_REVEALAGE, oit::REVEALAGE_FORMAT),
+    ] {
+        graph.add_texture(
+            name,
+            TextureSlot {
+                format,
+                size: TextureSize::Target,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+        );
+    }
+
+    graph.add_node(MAIN_PASS, MainPassNode).unwrap();
+    graph.add_node(OIT_ACCUMULATE, OitAccumulateNode).unwrap();
+    graph.add_node(OIT_COMPOSITE, OitCompositeNode).unwrap();
+    graph.add_node(DEBUG_LINES, DebugLinesNode).unwrap();
+
+    graph
+}
+
+// Culls and draws the meshes. The first view of a target clears it, later ones
+// only clear the depth under their viewport.
+struct MainPassNode;
+
+impl Node for MainPassNode {
+    fn outputs(&self) -> &[&str] {
+        &[VIEW_TARGET, VIEW_DEPTH]
+    }
+
+    fn run(&mut self, context: &NodeContext, encoder: &mut wgpu::CommandEncoder) {
+        let renderer = context.renderer;
+        let view = context.view;
+
+        let aspect = view.extent.x as f32 / view.extent.y as f32;
+        let frustum = view.camera.get_frustum(aspect);
+
+        let (color_load, depth_load) = match view.first {
+            true => (
+                wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0,
+                }),
+                wgpu::LoadOp::Clear(1.0),
+            ),
+            false => (wgpu::LoadOp::Load, wgpu::LoadOp::Load),
+        };
+
+        if let Some(culling) = &renderer.culling {
+            culling.dispatch(encoder);
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[
+                // This is what @location(0) in the fragment shader targets
+                Some(wgpu::RenderPassColorAttachment {
+                    view: context.texture(VIEW_TARGET),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: color_load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: context.texture(VIEW_DEPTH),
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        set_viewport(&mut render_pass, view.offset, view.extent);
+
+        if !view.first {
+            render_pass.set_pipeline(&renderer.depth_clear_pipeline);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        render_pass.set_bind_group(0, &renderer.uniform_bind_group, &[]);
+
+        if let Some(pipeline) = renderer.debug_view.barycentric_pipeline(renderer.view_mode) {
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(1, &renderer.debug_view.uniform_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, renderer.debug_view.barycentric_buffer().slice(..));
+            render_pass.set_vertex_buffer(1, renderer.instance_buffer.buffer().slice(..));
+
+            let visible_meshes = renderer
+                .meshes
+                .iter()
+                .filter(|mesh| mesh.is_visible(&frustum));
+
+            // Vertices are laid out like the index buffer, one per index.
+            for mesh in visible_meshes {
+                render_pass.draw(
+                    mesh.first_index..mesh.first_index + mesh.index_count,
+                    mesh.instance..mesh.instance + 1,
+                );
+            }
+        } else {
+            let filter = match renderer.draws_oit(view) {
+                true => MeshFilter::ExceptOit,
+                false => MeshFilter::All,
+            };
+
+            renderer.draw_meshes(
+                &mut render_pass,
+                Some(&frustum),
+                &view.camera.get_view_matrix(),
+                view.texture,
+                filter,
+            );
+        }
+    }
+}
+
+// Sums the transparent meshes of views with weighted blended transparency into
+// the accumulation targets, tested against the depth of the main pass.
+struct OitAccumulateNode;
+
+impl Node for OitAccumulateNode {
+    fn outputs(&self) -> &[&str] {
+        &[OIT_ACCUM, OIT_REVEALAGE, VIEW_DEPTH]
+    }
+
+    // The accumulation targets are only allocated for views drawing to them.
+    fn is_active(&self, renderer: &Renderer, view: &ViewInfo) -> bool {
+        renderer.draws_oit(view)
+    }
+
+    fn run(&mut self, context: &NodeContext, encoder: &mut wgpu::CommandEncoder) {
+        let renderer = context.renderer;
+        let view = context.view;
+
+        let aspect = view.extent.x as f32 / view.extent.y as f32;
+        let frustum = view.camera.get_frustum(aspect);
+
+        let target = |name, clear| {
+            Some(wgpu::RenderPassColorAttachment {
+                view: context.texture(name),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OIT Accumulate Pass"),
+            color_attachments: &[
+                target(OIT_ACCUM, wgpu::Color::TRANSPARENT),
+                target(OIT_REVEALAGE, wgpu::Color::WHITE),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: context.texture(VIEW_DEPTH),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        set_viewport(&mut render_pass, view.offset, view.extent);
+        render_pass.set_bind_group(0, &renderer.uniform_bind_group, &[]);
+
+        renderer.draw_meshes(
+            &mut render_pass,
+            Some(&frustum),
+            &view.camera.get_view_matrix(),
+            view.texture,
+            MeshFilter::Oit,
+        );
+    }
+}
+
+// Blends the accumulated transparency over the view's viewport.
+struct OitCompositeNode;
+
+impl Node for OitCompositeNode {
+    fn inputs(&self) -> &[&str] {
+        &[OIT_ACCUM, OIT_REVEALAGE]
+    }
+
+    // The depth is only attached, not written.
+    fn outputs(&self) -> &[&str] {
+        &[VIEW_TARGET, VIEW_DEPTH]
+    }
+
+    fn is_active(&self, renderer: &Renderer, view: &ViewInfo) -> bool {
+        renderer.draws_oit(view)
+    }
+
+    fn run(&mut self, context: &NodeContext, encoder: &mut wgpu::CommandEncoder) {
+        let renderer = context.renderer;
+
+        let bind_group = renderer.oit.bind_group(
+            renderer.device(),
+            context.texture(OIT_ACCUM),
+            context.texture(OIT_REVEALAGE),
+        );
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("OIT Composite Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: context.texture(VIEW_TARGET),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: context.texture(VIEW_DEPTH),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        set_viewport(&mut render_pass, context.view.offset, context.view.extent);
+        render_pass.set_pipeline(&renderer.oit.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+// Draws this frame's debug lines over the scene.
+struct DebugLinesNode;
+
+impl Node for DebugLinesNode {
+    fn outputs(&self) -> &[&str] {
+        &[VIEW_TARGET, VIEW_DEPTH]
+    }
+
+    fn run(&mut self, context: &NodeContext, encoder: &mut wgpu::CommandEncoder) {
+        let renderer = context.renderer;
+
+        if renderer.line_renderer.is_empty() {
+            return;
+        }
+
+        let mut line_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Debug Line Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: context.texture(VIEW_TARGET),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: context.texture(VIEW_DEPTH),
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            ..Default::default()
+        });
+
+        set_viewport(&mut line_pass, context.view.offset, context.view.extent);
+        line_pass.set_bind_group(0, &renderer.uniform_bind_group, &[]);
+        renderer.line_renderer.draw(&mut line_pass);
+    }
+}
+
+// Bounding sphere of a draw for GPU culling, radius in w.
+fn draw_sphere(sphere: &BoundingSphere) -> [f32; 4] {
+    sphere.center.extend(sphere.radius).to_array()
+}
 
-    return device.create_render_pipeline(&descriptor);
+fn aabb_sphere(aabb: &Aabb) -> BoundingSphere {
+    BoundingSphere::new(aabb.center(), aabb.half_extents().length())
 }
@@ -0,0 +1,199 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    rendering::preprocessor::{preprocess, PreprocessError, ShaderDefs},
+    watcher::FileWatcher,
+};
+
+pub const SHADER_DIR: &str = "assets/shaders";
+
+// Shaders shipped with the engine, embedded so release builds don't need the
+// assets folder.
+const BUILTIN_SHADERS: &[(&str, &str)] = &[
+    (
+        "shader.wgsl",
+        include_str!("../../assets/shaders/shader.wgsl"),
+    ),
+    ("mesh.wgsl", include_str!("../../assets/shaders/mesh.wgsl")),
+    ("oit.wgsl", include_str!("../../assets/shaders/oit.wgsl")),
+    (
+        "scene_material.wgsl",
+        include_str!("../../assets/shaders/scene_material.wgsl"),
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ShaderSource {
+    Wgsl(Cow<'static, str>),
+    // Relative to `SHADER_DIR`, reloaded when the file changes in debug builds.
+    Path(Cow<'static, str>),
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io { path: String, error: io::Error },
+    Preprocess(PreprocessError),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io { path, error } => write!(f, "Can't read {}: {}", path, error),
+            Self::Preprocess(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl From<PreprocessError> for ShaderError {
+    fn from(err: PreprocessError) -> Self {
+        Self::Preprocess(err)
+    }
+}
+
+// Shader files read from disk, keyed by their path relative to the root, and
+// the variants preprocessed from them. In debug builds the files are watched so
+// pipelines can be rebuilt without restarting the app.
+pub struct ShaderLibrary {
+    root: PathBuf,
+    sources: HashMap<String, String>,
+    variants: HashMap<(ShaderSource, ShaderDefs), String>,
+    watcher: Option<FileWatcher>,
+}
+
+impl ShaderLibrary {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let watcher = match cfg!(debug_assertions) {
+            true => FileWatcher::new()
+                .map_err(|err| log::warn!("Shader hot reloading disabled: {}", err))
+                .ok(),
+            false => None,
+        };
+
+        Self {
+            root: root.into(),
+            sources: HashMap::new(),
+            variants: HashMap::new(),
+            watcher,
+        }
+    }
+
+    // Source of a shader with its directives expanded for `defs`. Included
+    // files are resolved against the root and watched like the shader itself.
+    pub fn process(
+        &mut self,
+        shader: &ShaderSource,
+        defs: &ShaderDefs,
+    ) -> Result<String, ShaderError> {
+        let key = (shader.clone(), defs.clone());
+        if let Some(source) = self.variants.get(&key) {
+            return Ok(source.clone());
+        }
+
+        let (name, source) = match shader {
+            ShaderSource::Wgsl(source) => ("<inline>", source.to_string()),
+            ShaderSource::Path(path) => (path.as_ref(), self.load_path(path)?),
+        };
+
+        let processed = preprocess(name, &source, defs, &mut |path| {
+            self.load_path(path).map_err(|err| err.to_string())
+        })?;
+
+        self.variants.insert(key, processed.clone());
+        Ok(processed)
+    }
+
+    // Preprocesses the embedded copy of a built-in shader, for when the one on
+    // disk doesn't compile.
+    pub fn process_embedded(&self, path: &str, defs: &ShaderDefs) -> Result<String, ShaderError> {
+        let source = embedded(path).ok_or_else(|| ShaderError::Io {
+            path: path.to_string(),
+            error: io::ErrorKind::NotFound.into(),
+        })?;
+
+        let processed = preprocess(path, source, defs, &mut |path| {
+            embedded(path)
+                .map(str::to_string)
+                .ok_or_else(|| "not a built-in shader".to_string())
+        })?;
+
+        Ok(processed)
+    }
+
+    // Reads the shaders changed on disk again, returning their paths.
+    pub fn reload_changed(&mut self) -> Vec<String> {
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.changed(),
+            None => return vec![],
+        };
+
+        let mut reloaded = vec![];
+
+        for file in changed {
+            let path = match file.strip_prefix(&self.root) {
+                Ok(path) => path.to_string_lossy().replace('\\', "/"),
+                Err(_) => continue,
+            };
+
+            match fs::read_to_string(&file) {
+                Ok(source) => {
+                    log::info!("Reloading shader {}", path);
+                    self.sources.insert(path.clone(), source);
+                    reloaded.push(path);
+                    // Any variant may include the file.
+                    self.variants.clear();
+                }
+                Err(err) => log::error!("Failed to reload shader {}: {}", path, err),
+            }
+        }
+
+        reloaded
+    }
+
+    // Built-in shaders are read from disk in debug builds so they can be edited
+    // live, release builds use the copy embedded at compile time.
+    fn load_path(&mut self, path: &str) -> Result<String, ShaderError> {
+        if let Some(source) = self.sources.get(path) {
+            return Ok(source.clone());
+        }
+
+        if let (Some(source), false) = (embedded(path), cfg!(debug_assertions)) {
+            return Ok(source.to_string());
+        }
+
+        let file = self.root.join(Path::new(path));
+        let source = match (fs::read_to_string(&file), embedded(path)) {
+            (Ok(source), _) => source,
+            (Err(err), Some(source)) => {
+                log::warn!("Using embedded {}: {}", path, err);
+                source.to_string()
+            }
+            (Err(error), None) => {
+                return Err(ShaderError::Io {
+                    path: path.to_string(),
+                    error,
+                })
+            }
+        };
+
+        if let Some(watcher) = &mut self.watcher {
+            watcher.watch(&file);
+        }
+
+        self.sources.insert(path.to_string(), source.clone());
+        Ok(source)
+    }
+}
+
+fn embedded(path: &str) -> Option<&'static str> {
+    BUILTIN_SHADERS
+        .iter()
+        .find(|(builtin, _)| *builtin == path)
+        .map(|(_, source)| *source)
+}
@@ -0,0 +1,14 @@
+// Fullscreen triangle at the far plane, clears the depth of a viewport without
+// touching the rest of the render target.
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+// Color writes are masked out, the output only has to match the target.
+@fragment
+fn fragment() -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0);
+}
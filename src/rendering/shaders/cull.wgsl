@@ -0,0 +1,76 @@
+// Culling compute shader
+
+const CULL_ENABLED: u32 = 1u;
+const FIRST_INSTANCE: u32 = 2u;
+
+struct CullUniform {
+    planes: array<vec4<f32>, 6>,
+    draw_count: u32,
+    flags: u32,
+};
+
+struct DrawData {
+    sphere: vec4<f32>,
+    index_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    instance: u32,
+};
+
+struct DrawIndexedIndirect {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> cull: CullUniform;
+@group(0) @binding(1)
+var<storage, read> draws: array<DrawData>;
+@group(0) @binding(2)
+var<storage, read> instances: array<mat4x4<f32>>;
+@group(0) @binding(3)
+var<storage, read_write> commands: array<DrawIndexedIndirect>;
+
+fn is_visible(center: vec3<f32>, radius: f32) -> bool {
+    for (var i = 0u; i < 6u; i += 1u) {
+        let plane = cull.planes[i];
+
+        if dot(plane.xyz, center) + plane.w < -radius {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+@compute @workgroup_size(64)
+fn cull_draws(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+
+    if index >= cull.draw_count {
+        return;
+    }
+
+    let draw = draws[index];
+    let model = instances[draw.instance];
+
+    let center = (model * vec4<f32>(draw.sphere.xyz, 1.0)).xyz;
+    let scale = max(length(model[0].xyz), max(length(model[1].xyz), length(model[2].xyz)));
+
+    var visible = true;
+    if (cull.flags & CULL_ENABLED) != 0u {
+        visible = is_visible(center, draw.sphere.w * scale);
+    }
+
+    var command: DrawIndexedIndirect;
+    command.index_count = draw.index_count;
+    command.instance_count = select(0u, 1u, visible);
+    command.first_index = draw.first_index;
+    command.base_vertex = draw.base_vertex;
+    command.first_instance = select(0u, draw.instance, (cull.flags & FIRST_INSTANCE) != 0u);
+
+    commands[index] = command;
+}
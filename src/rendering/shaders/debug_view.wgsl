@@ -0,0 +1,153 @@
+// Vertex shader
+
+struct UniformBufferObject {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> ubo: UniformBufferObject;
+
+struct DebugViewUniform {
+    znear: f32,
+    zfar: f32,
+    orthographic: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> debug_view: DebugViewUniform;
+
+struct VertexInput {
+	@location(0) position: vec3<f32>,
+	@location(1) color: vec3<f32>,
+	@location(2) tex_coords: vec2<f32>,
+	@location(3) normal: vec3<f32>
+}
+
+struct BarycentricVertexInput {
+	@location(0) position: vec3<f32>,
+	@location(4) barycentric: vec3<f32>
+}
+
+struct ModelInput {
+    @location(5) x: vec4<f32>,
+    @location(6) y: vec4<f32>,
+    @location(7) z: vec4<f32>,
+    @location(8) w: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+	@location(0) color: vec3<f32>,
+	@location(1) tex_coords: vec2<f32>,
+	@location(2) normal: vec3<f32>,
+	@location(3) depth: f32,
+	@location(4) barycentric: vec3<f32>,
+	@location(5) @interpolate(flat) instance: u32,
+};
+
+// Distance from the camera, linear between the near and far planes.
+fn view_depth(clip: vec4<f32>) -> f32 {
+    if debug_view.orthographic != 0u {
+        return mix(debug_view.znear, debug_view.zfar, clip.z / clip.w);
+    }
+
+    return clip.w;
+}
+
+@vertex
+fn vertex(v: VertexInput, m: ModelInput, @builtin(instance_index) instance: u32) -> VertexOutput {
+    let model = mat4x4<f32>(m.x, m.y, m.z, m.w);
+
+    var out: VertexOutput;
+    out.position = ubo.view_proj * model * vec4<f32>(v.position, 1.0);
+    out.color = v.color;
+    out.tex_coords = v.tex_coords;
+    out.normal = (model * vec4<f32>(v.normal, 0.0)).xyz;
+    out.depth = view_depth(out.position);
+    out.barycentric = vec3<f32>(0.0);
+    out.instance = instance;
+
+    return out;
+}
+
+@vertex
+fn vertex_barycentric(v: BarycentricVertexInput, m: ModelInput) -> VertexOutput {
+    let model = mat4x4<f32>(m.x, m.y, m.z, m.w);
+
+    var out: VertexOutput;
+    out.position = ubo.view_proj * model * vec4<f32>(v.position, 1.0);
+    out.color = vec3<f32>(0.0);
+    out.tex_coords = vec2<f32>(0.0);
+    out.normal = vec3<f32>(0.0);
+    out.depth = view_depth(out.position);
+    out.barycentric = v.barycentric;
+    out.instance = 0u;
+
+    return out;
+}
+
+// Fragment shader
+
+const WIREFRAME_COLOR: vec3<f32> = vec3<f32>(0.9, 0.9, 0.9);
+
+@fragment
+fn fragment_wireframe(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(WIREFRAME_COLOR, 1.0);
+}
+
+@fragment
+fn fragment_wireframe_barycentric(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Keep edges about one pixel wide regardless of distance.
+    let width = fwidth(in.barycentric);
+    let edge = smoothstep(vec3<f32>(0.0), width, in.barycentric);
+    let coverage = 1.0 - min(edge.x, min(edge.y, edge.z));
+
+    if coverage < 0.5 {
+        discard;
+    }
+
+    return vec4<f32>(WIREFRAME_COLOR, 1.0);
+}
+
+@fragment
+fn fragment_normals(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(in.normal) * 0.5 + 0.5, 1.0);
+}
+
+@fragment
+fn fragment_uvs(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(fract(in.tex_coords), 0.0, 1.0);
+}
+
+@fragment
+fn fragment_vertex_colors(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
+
+@fragment
+fn fragment_depth(in: VertexOutput) -> @location(0) vec4<f32> {
+    let depth = (in.depth - debug_view.znear) / (debug_view.zfar - debug_view.znear);
+    return vec4<f32>(vec3<f32>(1.0 - saturate(depth)), 1.0);
+}
+
+// Blended additively, overlapping fragments accumulate towards white.
+@fragment
+fn fragment_overdraw(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.1, 0.04, 0.02, 1.0);
+}
+
+fn hash(value: u32) -> u32 {
+    var x = value;
+    x = ((x >> 16u) ^ x) * 0x45d9f3bu;
+    x = ((x >> 16u) ^ x) * 0x45d9f3bu;
+    x = (x >> 16u) ^ x;
+    return x;
+}
+
+@fragment
+fn fragment_mesh_id(in: VertexOutput) -> @location(0) vec4<f32> {
+    let h = hash(in.instance + 1u);
+    let color = vec3<f32>(f32(h & 0xffu), f32((h >> 8u) & 0xffu), f32((h >> 16u) & 0xffu)) / 255.0;
+
+    return vec4<f32>(color, 1.0);
+}
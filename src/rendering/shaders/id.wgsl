@@ -0,0 +1,43 @@
+// Vertex shader
+
+struct UniformBufferObject {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> ubo: UniformBufferObject;
+
+struct VertexInput {
+	@location(0) position: vec3<f32>,
+}
+
+struct ModelInput {
+    @location(5) x: vec4<f32>,
+    @location(6) y: vec4<f32>,
+    @location(7) z: vec4<f32>,
+    @location(8) w: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+	@location(0) @interpolate(flat) id: u32,
+};
+
+@vertex
+fn vertex(v: VertexInput, m: ModelInput, @builtin(instance_index) instance: u32) -> VertexOutput {
+    let model = mat4x4<f32>(m.x, m.y, m.z, m.w);
+
+    var out: VertexOutput;
+    out.position = ubo.view_proj * model * vec4<f32>(v.position, 1.0);
+    // Zero is reserved for the cleared background.
+    out.id = instance + 1u;
+
+    return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) u32 {
+    return in.id;
+}
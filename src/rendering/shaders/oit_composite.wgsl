@@ -0,0 +1,35 @@
+// Fullscreen triangle resolving weighted blended transparency over the view.
+
+@group(0) @binding(0)
+var accum_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var revealage_texture: texture_2d<f32>;
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+@fragment
+fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let revealage = textureLoad(revealage_texture, coords, 0).r;
+
+    // Nothing transparent covers the pixel.
+    if revealage >= 1.0 {
+        discard;
+    }
+
+    var accum = textureLoad(accum_texture, coords, 0);
+
+    // Sums that overflowed the half floats still average to a usable color.
+    let largest = max(max(abs(accum.r), abs(accum.g)), abs(accum.b));
+    if largest > 65504.0 {
+        accum = vec4<f32>(accum.aaa, accum.a);
+    }
+
+    let color = accum.rgb / max(accum.a, 0.00001);
+
+    return vec4<f32>(color, 1.0 - revealage);
+}
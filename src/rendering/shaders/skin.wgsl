@@ -0,0 +1,116 @@
+// Skinning compute shader, applies morph targets and then the skin.
+
+struct SkinJob {
+    source: u32,
+    destination: u32,
+    vertex_count: u32,
+    joints: u32,
+    morph_targets: u32,
+    morph_target_count: u32,
+    morph_weights: u32,
+    padding: u32,
+};
+
+struct SkinVertex {
+    joints: vec4<u32>,
+    weights: vec4<f32>,
+};
+
+// `Vertex` is 11 tightly packed floats and `MorphDelta` 9, which no WGSL
+// struct matches.
+const VERTEX_STRIDE: u32 = 11u;
+const POSITION: u32 = 0u;
+const NORMAL: u32 = 8u;
+
+const DELTA_STRIDE: u32 = 9u;
+const DELTA_POSITION: u32 = 0u;
+const DELTA_NORMAL: u32 = 3u;
+
+@group(0) @binding(0)
+var<storage, read> jobs: array<SkinJob>;
+@group(0) @binding(1)
+var<storage, read> sources: array<f32>;
+@group(0) @binding(2)
+var<storage, read> skins: array<SkinVertex>;
+@group(0) @binding(3)
+var<storage, read> joints: array<mat4x4<f32>>;
+@group(0) @binding(4)
+var<storage, read> morph_targets: array<f32>;
+@group(0) @binding(5)
+var<storage, read> morph_weights: array<f32>;
+@group(0) @binding(6)
+var<storage, read_write> vertices: array<f32>;
+
+fn read_source(index: u32) -> vec3<f32> {
+    return vec3<f32>(sources[index], sources[index + 1u], sources[index + 2u]);
+}
+
+fn read_delta(index: u32) -> vec3<f32> {
+    return vec3<f32>(morph_targets[index], morph_targets[index + 1u], morph_targets[index + 2u]);
+}
+
+fn write_vec3(index: u32, value: vec3<f32>) {
+    vertices[index] = value.x;
+    vertices[index + 1u] = value.y;
+    vertices[index + 2u] = value.z;
+}
+
+@compute @workgroup_size(64)
+fn skin_vertices(@builtin(global_invocation_id) id: vec3<u32>) {
+    let job = jobs[id.y];
+
+    if id.x >= job.vertex_count {
+        return;
+    }
+
+    let source = (job.source + id.x) * VERTEX_STRIDE;
+    let destination = (job.destination + id.x) * VERTEX_STRIDE;
+
+    var position = read_source(source + POSITION);
+    var normal = read_source(source + NORMAL);
+
+    for (var target_index = 0u; target_index < job.morph_target_count; target_index += 1u) {
+        let weight = morph_weights[job.morph_weights + target_index];
+        let delta = (job.morph_targets + target_index * job.vertex_count + id.x) * DELTA_STRIDE;
+
+        position += read_delta(delta + DELTA_POSITION) * weight;
+        normal += read_delta(delta + DELTA_NORMAL) * weight;
+    }
+
+    let skin = skins[job.source + id.x];
+    let total = dot(skin.weights, vec4<f32>(1.0));
+
+    var matrix = mat4x4<f32>(
+        vec4<f32>(1.0, 0.0, 0.0, 0.0),
+        vec4<f32>(0.0, 1.0, 0.0, 0.0),
+        vec4<f32>(0.0, 0.0, 1.0, 0.0),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0),
+    );
+
+    // Vertices without weights keep their rest pose.
+    if total > 0.0 {
+        matrix = mat4x4<f32>(vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0));
+
+        for (var i = 0u; i < 4u; i += 1u) {
+            let weight = skin.weights[i];
+            if weight > 0.0 {
+                matrix += joints[job.joints + skin.joints[i]] * (weight / total);
+            }
+        }
+    }
+
+    // Colors and texture coordinates are copied as is.
+    for (var i = 0u; i < VERTEX_STRIDE; i += 1u) {
+        vertices[destination + i] = sources[source + i];
+    }
+
+    let posed_position = matrix * vec4<f32>(position, 1.0);
+    let posed_normal = (matrix * vec4<f32>(normal, 0.0)).xyz;
+
+    write_vec3(destination + POSITION, posed_position.xyz);
+    // Zero normals stay zero instead of turning into NaN.
+    write_vec3(
+        destination + NORMAL,
+        select(vec3<f32>(0.0), normalize(posed_normal), dot(posed_normal, posed_normal) > 0.0),
+    );
+}
@@ -0,0 +1,516 @@
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+
+use crate::{
+    loader::MeshData,
+    rendering::{
+        bounds::Aabb,
+        megabuffer::MegaBuffer,
+        vertex::{MorphDelta, SkinVertex, Vertex},
+    },
+};
+
+const WORKGROUP_SIZE: u32 = 64;
+
+// Joints deforming the mesh of this entity, `SkinVertex::joints` index into
+// them. The mesh's own transform is ignored in favor of the joints', as glTF
+// requires.
+#[derive(Component, Clone, Debug)]
+pub struct Skin {
+    pub joints: Vec<Entity>,
+    // One per joint, from mesh space to the joint's bind pose.
+    pub inverse_bind_matrices: Arc<[Mat4]>,
+}
+
+// Ranges of a deformed mesh in the skinning buffers, laid out like
+// `skin.wgsl`.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkinJob {
+    // Rest pose in `sources` and joint weights in `skins`.
+    source: u32,
+    // Posed vertices in the shared vertex buffer.
+    destination: u32,
+    vertex_count: u32,
+    // Joint matrices in `joints`.
+    joints: u32,
+    // Deltas in `morph_targets`, one target after the other.
+    morph_targets: u32,
+    morph_target_count: u32,
+    // One per target in `morph_weights`.
+    morph_weights: u32,
+    _padding: u32,
+}
+
+struct SkinRanges {
+    job: SkinJob,
+    vertex_capacity: u32,
+    joint_capacity: u32,
+    morph_capacity: u32,
+    morph_weight_capacity: u32,
+}
+
+pub fn is_supported(adapter: &wgpu::Adapter) -> bool {
+    let flags = adapter.get_downlevel_capabilities().flags;
+    flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+}
+
+// Poses skinned and morphed meshes every frame by writing their vertices over
+// the ones in the shared vertex buffer, so every pipeline draws them without
+// knowing about deformations. Morph targets are applied first, then the skin.
+// Runs as a compute pass, or on the CPU without compute support.
+pub struct Skinning {
+    sources: MegaBuffer<Vertex>,
+    skins: MegaBuffer<SkinVertex>,
+    joints: MegaBuffer<[[f32; 4]; 4]>,
+    morph_targets: MegaBuffer<MorphDelta>,
+    morph_weights: MegaBuffer<f32>,
+    jobs: MegaBuffer<SkinJob>,
+
+    ranges: Vec<SkinRanges>,
+
+    compute: Option<SkinningPipeline>,
+}
+
+struct SkinningPipeline {
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Skinning {
+    pub fn new(device: &wgpu::Device, compute: bool) -> Self {
+        let storage = wgpu::BufferUsages::STORAGE;
+
+        Self {
+            sources: MegaBuffer::new(device, "skin_source_buffer", storage),
+            skins: MegaBuffer::new(device, "skin_weight_buffer", storage),
+            joints: MegaBuffer::new(device, "joint_buffer", storage),
+            morph_targets: MegaBuffer::new(device, "morph_target_buffer", storage),
+            morph_weights: MegaBuffer::new(device, "morph_weight_buffer", storage),
+            jobs: MegaBuffer::new(device, "skin_job_buffer", storage),
+            ranges: vec![],
+            compute: compute.then(|| SkinningPipeline::new(device)),
+        }
+    }
+
+    // Usage the shared vertex buffer needs to be written by this pass.
+    pub fn vertex_usage(&self) -> wgpu::BufferUsages {
+        match self.compute {
+            Some(_) => wgpu::BufferUsages::STORAGE,
+            None => wgpu::BufferUsages::empty(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // Registers a mesh whose vertices start at `target` in the shared vertex
+    // buffer. Joints start out as identity and weights as zero, drawing the
+    // rest pose.
+    pub fn add(&mut self, data: &MeshData, target: i32) -> u32 {
+        let joint_count = joint_count(&data.skin);
+        let morph_target_count = data.morph_target_count();
+
+        let job = SkinJob {
+            source: self.sources.push(&data.vertices),
+            destination: target as u32,
+            vertex_count: data.vertices.len() as u32,
+            joints: self
+                .joints
+                .push(&vec![Mat4::IDENTITY.to_cols_array_2d(); joint_count]),
+            morph_targets: self.morph_targets.push(&data.morph_targets),
+            morph_target_count: morph_target_count as u32,
+            morph_weights: self.morph_weights.push(&vec![0.0; morph_target_count]),
+            _padding: 0,
+        };
+        self.skins.push(&skin_or_default(data));
+        self.jobs.push(&[job]);
+
+        self.ranges.push(SkinRanges {
+            job,
+            vertex_capacity: job.vertex_count,
+            joint_capacity: joint_count as u32,
+            morph_capacity: data.morph_targets.len() as u32,
+            morph_weight_capacity: morph_target_count as u32,
+        });
+
+        self.ranges.len() as u32 - 1
+    }
+
+    // Replaces the rest pose of a mesh, reusing its ranges when it fits.
+    pub fn update(&mut self, index: u32, data: &MeshData, target: i32) {
+        let ranges = &mut self.ranges[index as usize];
+        let vertex_count = data.vertices.len() as u32;
+        let skin = skin_or_default(data);
+
+        if vertex_count <= ranges.vertex_capacity {
+            self.sources
+                .write(ranges.job.source as usize, &data.vertices);
+            self.skins.write(ranges.job.source as usize, &skin);
+        } else {
+            ranges.job.source = self.sources.push(&data.vertices);
+            self.skins.push(&skin);
+            ranges.vertex_capacity = vertex_count;
+        }
+
+        if data.morph_targets.len() as u32 <= ranges.morph_capacity {
+            self.morph_targets
+                .write(ranges.job.morph_targets as usize, &data.morph_targets);
+        } else {
+            ranges.job.morph_targets = self.morph_targets.push(&data.morph_targets);
+            ranges.morph_capacity = data.morph_targets.len() as u32;
+        }
+
+        // Weights are kept, unless there are more targets than before.
+        let morph_target_count = data.morph_target_count() as u32;
+        if morph_target_count > ranges.morph_weight_capacity {
+            ranges.job.morph_weights = self
+                .morph_weights
+                .push(&vec![0.0; morph_target_count as usize]);
+            ranges.morph_weight_capacity = morph_target_count;
+        }
+
+        ranges.job.destination = target as u32;
+        ranges.job.vertex_count = vertex_count;
+        ranges.job.morph_target_count = morph_target_count;
+        self.jobs.set(index as usize, ranges.job);
+    }
+
+    // Stops posing a mesh that is no longer deformed, its ranges are left unused.
+    pub fn remove(&mut self, index: u32) {
+        let ranges = &mut self.ranges[index as usize];
+        ranges.job.vertex_count = 0;
+        self.jobs.set(index as usize, ranges.job);
+    }
+
+    // Sets the matrices from mesh space to the posed mesh space of each joint.
+    pub fn set_joints(&mut self, index: u32, matrices: &[Mat4]) {
+        let ranges = &mut self.ranges[index as usize];
+        let matrices: Vec<_> = matrices.iter().map(Mat4::to_cols_array_2d).collect();
+
+        if matrices.len() as u32 <= ranges.joint_capacity {
+            self.joints.write(ranges.job.joints as usize, &matrices);
+        } else {
+            ranges.job.joints = self.joints.push(&matrices);
+            ranges.joint_capacity = matrices.len() as u32;
+            self.jobs.set(index as usize, ranges.job);
+        }
+    }
+
+    // Weights past the mesh's targets are ignored, missing ones are zero.
+    pub fn set_morph_weights(&mut self, index: u32, weights: &[f32]) {
+        let ranges = &self.ranges[index as usize];
+        let count = ranges.job.morph_target_count as usize;
+
+        let mut resized = vec![0.0; count];
+        for (weight, value) in resized.iter_mut().zip(weights) {
+            *weight = *value;
+        }
+
+        self.morph_weights
+            .write(ranges.job.morph_weights as usize, &resized);
+    }
+
+    // Without compute support the posed vertices are written to the CPU copy
+    // of the vertex buffer, before it's uploaded.
+    pub fn skin_on_cpu(&self, vertex_buffer: &mut MegaBuffer<Vertex>) {
+        if self.compute.is_some() {
+            return;
+        }
+
+        let joints = self.joints.as_slice();
+        let morph_targets = self.morph_targets.as_slice();
+        let morph_weights = self.morph_weights.as_slice();
+
+        for ranges in &self.ranges {
+            let job = ranges.job;
+            let source = job.source as usize..(job.source + job.vertex_count) as usize;
+
+            let skinned: Vec<_> = self.sources.as_slice()[source.clone()]
+                .iter()
+                .zip(&self.skins.as_slice()[source])
+                .enumerate()
+                .map(|(index, (vertex, skin))| {
+                    let mut position = Vec3::from(vertex.position);
+                    let mut normal = Vec3::from(vertex.normal);
+
+                    for target in 0..job.morph_target_count {
+                        let weight = morph_weights[(job.morph_weights + target) as usize];
+                        let delta = &morph_targets
+                            [(job.morph_targets + target * job.vertex_count) as usize + index];
+
+                        position += Vec3::from(delta.position) * weight;
+                        normal += Vec3::from(delta.normal) * weight;
+                    }
+
+                    let matrix = skin_matrix(skin, |joint| {
+                        joints
+                            .get((job.joints + joint) as usize)
+                            .map(Mat4::from_cols_array_2d)
+                            .unwrap_or(Mat4::IDENTITY)
+                    });
+
+                    Vertex {
+                        position: matrix.transform_point3(position).into(),
+                        normal: matrix.transform_vector3(normal).normalize_or_zero().into(),
+                        ..*vertex
+                    }
+                })
+                .collect();
+
+            vertex_buffer.write(job.destination as usize, &skinned);
+        }
+    }
+
+    // Uploads the skinning buffers. `vertices_reallocated` must be set whenever
+    // the shared vertex buffer was recreated since the last call.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertex_buffer: &wgpu::Buffer,
+        vertices_reallocated: bool,
+    ) {
+        let mut rebind = vertices_reallocated;
+        for buffer_reallocated in [
+            self.sources.upload(device, queue),
+            self.skins.upload(device, queue),
+            self.joints.upload(device, queue),
+            self.morph_targets.upload(device, queue),
+            self.morph_weights.upload(device, queue),
+            self.jobs.upload(device, queue),
+        ] {
+            rebind |= buffer_reallocated;
+        }
+
+        let compute = match &mut self.compute {
+            Some(compute) => compute,
+            None => return,
+        };
+
+        if rebind || compute.bind_group.is_none() {
+            let buffers = [
+                self.jobs.buffer(),
+                self.sources.buffer(),
+                self.skins.buffer(),
+                self.joints.buffer(),
+                self.morph_targets.buffer(),
+                self.morph_weights.buffer(),
+                vertex_buffer,
+            ];
+
+            let entries: Vec<_> = buffers
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect();
+
+            compute.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &compute.bind_group_layout,
+                entries: &entries,
+                label: Some("skin_bind_group"),
+            }));
+        }
+    }
+
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder) {
+        let (compute, bind_group) = match &self.compute {
+            Some(compute) if !self.ranges.is_empty() => match &compute.bind_group {
+                Some(bind_group) => (compute, bind_group),
+                None => return,
+            },
+            _ => return,
+        };
+
+        let max_vertices = self
+            .ranges
+            .iter()
+            .map(|ranges| ranges.job.vertex_count)
+            .max()
+            .unwrap_or(0);
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Skin Pass"),
+            timestamp_writes: None,
+        });
+
+        // One row of workgroups per mesh.
+        compute_pass.set_pipeline(&compute.pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            max_vertices.div_ceil(WORKGROUP_SIZE),
+            self.ranges.len() as u32,
+            1,
+        );
+    }
+}
+
+impl SkinningPipeline {
+    fn new(device: &wgpu::Device) -> Self {
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                storage(0, true),
+                storage(1, true),
+                storage(2, true),
+                storage(3, true),
+                storage(4, true),
+                storage(5, true),
+                storage(6, false),
+            ],
+            label: Some("skin_bind_group_layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("skin_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/skin.wgsl"));
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("skin_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "skin_vertices",
+        });
+
+        Self {
+            bind_group_layout,
+            bind_group: None,
+            pipeline,
+        }
+    }
+}
+
+// Blends the joint matrices by weight. Vertices without weights keep their
+// rest pose.
+pub fn skin_matrix(skin: &SkinVertex, joint: impl Fn(u32) -> Mat4) -> Mat4 {
+    let total: f32 = skin.weights.iter().sum();
+    if total <= 0.0 {
+        return Mat4::IDENTITY;
+    }
+
+    skin.joints
+        .iter()
+        .zip(skin.weights)
+        .filter(|(_, weight)| *weight > 0.0)
+        .fold(Mat4::ZERO, |matrix, (index, weight)| {
+            matrix + joint(*index) * (weight / total)
+        })
+}
+
+// Local bounds of a skinned or morphed mesh in its current pose. Morph targets
+// grow the rest bounds by their largest delta times their weight, then every
+// joint moves a copy of them: a skinned vertex is a blend of its morphed
+// position moved by its joints, so it stays inside their union.
+pub struct DeformBounds {
+    rest: Aabb,
+    // Largest position delta of each morph target, per axis.
+    morph_extents: Vec<Vec3>,
+    // Vertices without weights aren't moved by any joint.
+    unweighted: bool,
+    weights: Vec<f32>,
+    joints: Vec<Mat4>,
+}
+
+impl DeformBounds {
+    pub fn new(data: &MeshData) -> Self {
+        let mut bounds = Self {
+            rest: data.aabb,
+            morph_extents: vec![],
+            unweighted: false,
+            weights: vec![],
+            joints: vec![],
+        };
+        bounds.set_mesh(data);
+
+        bounds
+    }
+
+    // Keeps the current pose, e.g. when the mesh is reloaded.
+    pub fn set_mesh(&mut self, data: &MeshData) {
+        self.rest = data.aabb;
+        self.morph_extents = match data.vertices.len() {
+            0 => vec![],
+            count => data
+                .morph_targets
+                .chunks(count)
+                .map(|target| {
+                    target.iter().fold(Vec3::ZERO, |extent, delta| {
+                        extent.max(Vec3::from(delta.position).abs())
+                    })
+                })
+                .collect(),
+        };
+        self.unweighted = skin_or_default(data)
+            .iter()
+            .any(|skin| skin.weights.iter().sum::<f32>() <= 0.0);
+    }
+
+    pub fn set_joints(&mut self, joints: &[Mat4]) {
+        self.joints = joints.to_vec();
+    }
+
+    pub fn set_morph_weights(&mut self, weights: &[f32]) {
+        self.weights = weights.to_vec();
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        let grow = self
+            .morph_extents
+            .iter()
+            .zip(&self.weights)
+            .fold(Vec3::ZERO, |grow, (extent, weight)| {
+                grow + *extent * weight.abs()
+            });
+        let morphed = Aabb::new(self.rest.min - grow, self.rest.max + grow);
+
+        let posed = self
+            .joints
+            .iter()
+            .map(|joint| morphed.transform(joint))
+            .reduce(|a, b| a.union(&b));
+
+        match posed {
+            Some(posed) if self.unweighted => posed.union(&morphed),
+            Some(posed) => posed,
+            None => morphed,
+        }
+    }
+}
+
+// Meshes that are only morphed get zero weights, leaving them unskinned.
+fn skin_or_default(data: &MeshData) -> Vec<SkinVertex> {
+    match data.skin.is_empty() {
+        true => vec![SkinVertex::default(); data.vertices.len()],
+        false => data.skin.clone(),
+    }
+}
+
+fn joint_count(skin: &[SkinVertex]) -> usize {
+    skin.iter()
+        .flat_map(|vertex| vertex.joints.iter().zip(vertex.weights))
+        .filter(|(_, weight)| *weight > 0.0)
+        .map(|(joint, _)| *joint as usize + 1)
+        .max()
+        .unwrap_or(0)
+}
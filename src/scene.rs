@@ -0,0 +1,762 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy_ecs::{event::ManualEventReader, prelude::*};
+use bevy_math::prelude::*;
+use gltf::animation::util::ReadOutputs;
+
+use crate::{
+    animation::{AnimationClip, AnimationTargets, Curve, Interpolation, Keyframes, MorphWeights},
+    asset::{server::update_assets, Asset, AssetEvent, AssetServer, Assets, Handle},
+    loader::{MeshData, Primitives},
+    plugin::Plugin,
+    rendering::{
+        camera::{Camera, Projection},
+        light::{Light, LightKind},
+        material::{BlendMode, CustomMaterial, MaterialBinding, MaterialState, TextureSource},
+        preprocessor::ShaderDefs,
+        shader::ShaderSource,
+        skinning::Skin,
+    },
+    schedule::PreUpdate,
+    transform::{Children, GlobalTransform, Parent, Transform},
+    AppBuilder,
+};
+
+#[derive(Component, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Name(pub String);
+
+// Camera of a scene node. Spawned on its own so it doesn't draw until the app
+// inserts the `Camera` built from it.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SceneCamera {
+    pub projection: Projection,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl SceneCamera {
+    // Looks down the -Z axis of the node.
+    pub fn camera(&self, transform: &GlobalTransform) -> Camera {
+        let eye = transform.translation();
+
+        Camera {
+            eye,
+            target: eye + transform.0.transform_vector3(Vec3::NEG_Z),
+            up: transform.0.transform_vector3(Vec3::Y),
+            projection: self.projection,
+            znear: self.znear,
+            zfar: self.zfar,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    // Alpha is ignored.
+    #[default]
+    Opaque,
+    // Fragments with alpha below the cutoff are discarded.
+    Mask(f32),
+    Blend,
+}
+
+// Base color of a glTF material, drawn with `assets/shaders/scene_material.wgsl`.
+pub struct SceneMaterial {
+    pub base_color: Vec4,
+    pub base_color_texture: Option<Handle<image::DynamicImage>>,
+    pub alpha_mode: AlphaMode,
+    // Both faces are drawn, back faces aren't culled.
+    pub double_sided: bool,
+}
+
+impl Asset for SceneMaterial {}
+
+impl CustomMaterial for SceneMaterial {
+    fn shader(&self) -> ShaderSource {
+        ShaderSource::Path("scene_material.wgsl".into())
+    }
+
+    fn defs(&self) -> ShaderDefs {
+        let mut defs = ShaderDefs::new();
+
+        if self.base_color_texture.is_some() {
+            defs = defs.with("BASE_COLOR_TEXTURE");
+        }
+
+        match self.alpha_mode {
+            AlphaMode::Opaque => defs.with("ALPHA_OPAQUE"),
+            AlphaMode::Mask(_) => defs.with("ALPHA_MASK"),
+            AlphaMode::Blend => defs,
+        }
+    }
+
+    fn bindings(&self) -> Vec<MaterialBinding> {
+        let alpha_cutoff = match self.alpha_mode {
+            AlphaMode::Mask(cutoff) => cutoff,
+            _ => 0.0,
+        };
+
+        // Padded to the 32 bytes the shader's uniform struct takes.
+        let uniform = [self.base_color, Vec4::new(alpha_cutoff, 0.0, 0.0, 0.0)];
+
+        let mut bindings = vec![MaterialBinding::Uniform(
+            bytemuck::cast_slice(&uniform.map(|v| v.to_array())).to_vec(),
+        )];
+
+        if let Some(texture) = self.base_color_texture {
+            bindings.push(MaterialBinding::Texture(TextureSource::Asset(texture)));
+        }
+
+        bindings
+    }
+
+    // Blended materials don't write depth so they don't hide the transparent
+    // meshes drawn after them.
+    fn state(&self) -> MaterialState {
+        MaterialState {
+            blend: match self.alpha_mode {
+                AlphaMode::Blend => BlendMode::Alpha,
+                _ => BlendMode::Opaque,
+            },
+            alpha_test: matches!(self.alpha_mode, AlphaMode::Mask(_)),
+            oit: self.alpha_mode == AlphaMode::Blend,
+            cull_mode: match self.double_sided {
+                true => None,
+                false => Some(wgpu::Face::Back),
+            },
+            depth_write: self.alpha_mode != AlphaMode::Blend,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct MaterialData {
+    pub base_color: Vec4,
+    // File path as the scene was loaded with, images embedded in the file
+    // aren't supported.
+    pub base_color_texture: Option<PathBuf>,
+    pub alpha_mode: AlphaMode,
+    pub double_sided: bool,
+}
+
+#[derive(PartialEq)]
+pub struct SceneNode {
+    pub name: Option<String>,
+    // Relative to the parent node.
+    pub transform: Mat4,
+    // Index into `Scene::meshes`.
+    pub mesh: Option<usize>,
+    // Index into `Scene::materials`, taken from the mesh's first primitive.
+    pub material: Option<usize>,
+    pub light: Option<Light>,
+    pub camera: Option<SceneCamera>,
+    // Index into `Scene::skins`, deforming the node's mesh.
+    pub skin: Option<usize>,
+    // Default morph target weights of the node's mesh.
+    pub weights: Option<Vec<f32>>,
+    // Indices into `Scene::nodes`.
+    pub children: Vec<usize>,
+}
+
+#[derive(PartialEq)]
+pub struct SkinData {
+    // Indices into `Scene::nodes`.
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Arc<[Mat4]>,
+}
+
+// Nodes of a glTF file's default scene, along with every mesh and material of
+// the file. The primitives of a mesh are merged into one.
+pub struct Scene {
+    pub meshes: Vec<Arc<MeshData>>,
+    pub materials: Vec<MaterialData>,
+    pub skins: Vec<SkinData>,
+    pub animations: Vec<Arc<AnimationClip>>,
+    pub nodes: Vec<SceneNode>,
+    pub roots: Vec<usize>,
+}
+
+impl Asset for Scene {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let (gltf, buffers, _) = gltf::import(path)?;
+
+        let meshes: Vec<Arc<MeshData>> = gltf
+            .meshes()
+            .map(|mesh| {
+                let mut primitives = Primitives::default();
+                primitives.read(mesh.primitives(), &buffers);
+
+                Arc::new(primitives.into_mesh())
+            })
+            .collect();
+
+        let materials = gltf
+            .materials()
+            .map(|material| {
+                let pbr = material.pbr_metallic_roughness();
+
+                let base_color_texture = pbr.base_color_texture().and_then(|info| {
+                    match info.texture().source().source() {
+                        gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                            Some(path.parent().unwrap_or(Path::new("")).join(uri))
+                        }
+                        _ => {
+                            log::warn!("Embedded images are not supported: {}", path.display());
+                            None
+                        }
+                    }
+                });
+
+                // The cutoff defaults to 0.5 when it's missing, as the spec says.
+                let alpha_mode = match material.alpha_mode() {
+                    gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                    gltf::material::AlphaMode::Mask => {
+                        AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5))
+                    }
+                    gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                };
+
+                MaterialData {
+                    base_color: Vec4::from(pbr.base_color_factor()),
+                    base_color_texture,
+                    alpha_mode,
+                    double_sided: material.double_sided(),
+                }
+            })
+            .collect();
+
+        let skins = gltf
+            .skins()
+            .map(|skin| {
+                let joints: Vec<_> = skin.joints().map(|joint| joint.index()).collect();
+
+                // Missing matrices are identity, as the spec says.
+                let inverse_bind_matrices = skin
+                    .reader(|buffer| Some(&buffers[buffer.index()]))
+                    .read_inverse_bind_matrices()
+                    .map(|iter| iter.map(|m| Mat4::from_cols_array_2d(&m)).collect())
+                    .unwrap_or_else(|| vec![Mat4::IDENTITY; joints.len()]);
+
+                SkinData {
+                    joints,
+                    inverse_bind_matrices: inverse_bind_matrices.into(),
+                }
+            })
+            .collect();
+
+        let animations = gltf
+            .animations()
+            .map(|animation| {
+                let curves = animation
+                    .channels()
+                    .filter_map(|channel| {
+                        let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                        let keyframes = match reader.read_outputs()? {
+                            ReadOutputs::Translations(iter) => {
+                                Keyframes::Translation(iter.map(Vec3::from).collect())
+                            }
+                            ReadOutputs::Rotations(iter) => {
+                                Keyframes::Rotation(iter.into_f32().map(Quat::from_array).collect())
+                            }
+                            ReadOutputs::Scales(iter) => {
+                                Keyframes::Scale(iter.map(Vec3::from).collect())
+                            }
+                            ReadOutputs::MorphTargetWeights(iter) => {
+                                Keyframes::Weights(iter.into_f32().collect())
+                            }
+                        };
+
+                        let curve = Curve {
+                            node: channel.target().node().index(),
+                            times: reader.read_inputs()?.collect(),
+                            keyframes,
+                            interpolation: match channel.sampler().interpolation() {
+                                gltf::animation::Interpolation::Step => Interpolation::Step,
+                                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                                gltf::animation::Interpolation::CubicSpline => {
+                                    Interpolation::CubicSpline
+                                }
+                            },
+                        };
+
+                        if !curve.is_valid() {
+                            log::warn!("Skipping malformed animation channel: {}", path.display());
+                            return None;
+                        }
+
+                        Some(curve)
+                    })
+                    .collect();
+
+                Arc::new(AnimationClip::new(
+                    animation.name().map(str::to_string),
+                    curves,
+                ))
+            })
+            .collect();
+
+        let nodes = gltf
+            .nodes()
+            .map(|node| SceneNode {
+                name: node.name().map(str::to_string),
+                transform: Mat4::from_cols_array_2d(&node.transform().matrix()),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                material: node
+                    .mesh()
+                    .and_then(|mesh| mesh.primitives().next())
+                    .and_then(|primitive| primitive.material().index()),
+                light: node.light().map(|light| Light {
+                    kind: match light.kind() {
+                        gltf::khr_lights_punctual::Kind::Directional => LightKind::Directional,
+                        gltf::khr_lights_punctual::Kind::Point => LightKind::Point,
+                        gltf::khr_lights_punctual::Kind::Spot {
+                            inner_cone_angle,
+                            outer_cone_angle,
+                        } => LightKind::Spot {
+                            inner_angle: inner_cone_angle,
+                            outer_angle: outer_cone_angle,
+                        },
+                    },
+                    color: Vec3::from(light.color()),
+                    intensity: light.intensity(),
+                    range: light.range(),
+                }),
+                camera: node.camera().map(|camera| match camera.projection() {
+                    gltf::camera::Projection::Perspective(perspective) => SceneCamera {
+                        projection: Projection::Perspective {
+                            fovy: perspective.yfov().to_degrees(),
+                        },
+                        znear: perspective.znear(),
+                        zfar: perspective.zfar().unwrap_or(f32::MAX),
+                    },
+                    gltf::camera::Projection::Orthographic(orthographic) => SceneCamera {
+                        projection: Projection::Orthographic {
+                            height: orthographic.ymag() * 2.0,
+                        },
+                        znear: orthographic.znear(),
+                        zfar: orthographic.zfar(),
+                    },
+                }),
+                skin: node.skin().map(|skin| skin.index()),
+                // Zero when neither the node nor its mesh has default weights.
+                weights: node
+                    .weights()
+                    .or_else(|| node.mesh().and_then(|mesh| mesh.weights()))
+                    .map(<[f32]>::to_vec)
+                    .or_else(|| {
+                        let mesh = &meshes[node.mesh()?.index()];
+                        let count = mesh.morph_target_count();
+                        (count > 0).then(|| vec![0.0; count])
+                    }),
+                children: node.children().map(|child| child.index()).collect(),
+            })
+            .collect();
+
+        let roots = gltf
+            .default_scene()
+            .or_else(|| gltf.scenes().next())
+            .map(|scene| scene.nodes().map(|node| node.index()).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            meshes,
+            materials,
+            skins,
+            animations,
+            nodes,
+            roots,
+        })
+    }
+}
+
+impl Scene {
+    // Whether the scenes only differ in the data of their meshes, materials and
+    // animations, so entities spawned from one also fit the other.
+    fn same_layout(&self, other: &Scene) -> bool {
+        self.nodes == other.nodes
+            && self.roots == other.roots
+            && self.skins == other.skins
+            && self.meshes.len() == other.meshes.len()
+            && self.materials.len() == other.materials.len()
+            && self.animations.len() == other.animations.len()
+    }
+}
+
+// Animations of a spawned scene, in file order. Play them with an
+// `AnimationPlayer` on the same entity.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SceneAnimations(pub Vec<Handle<AnimationClip>>);
+
+// Marks an entity whose `Handle<Scene>` was spawned, with the entities of the
+// scene's root nodes among its children.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpawnedScene(pub Vec<Entity>);
+
+// Meshes, materials and clips of a loaded scene, shared by every spawn of it.
+// Reloading the scene writes the new assets into the same handles.
+#[derive(Clone)]
+struct SceneHandles {
+    meshes: Vec<Handle<MeshData>>,
+    materials: Vec<Handle<SceneMaterial>>,
+    animations: Vec<Handle<AnimationClip>>,
+}
+
+// Handles of every spawned scene, along with the version they were made from.
+#[derive(Resource, Default)]
+struct LoadedScenes(HashMap<Handle<Scene>, (Arc<Scene>, SceneHandles)>);
+
+// Spawns a loaded scene under a new root entity, one entity per node with its
+// `Transform`, `Name`, mesh and material handles, `Light`, `SceneCamera`,
+// `Skin` and `MorphWeights`. The root gets the `SceneAnimations`.
+// None when the scene isn't loaded yet.
+pub fn spawn_scene(world: &mut World, scene: Handle<Scene>) -> Option<Entity> {
+    let loaded = world.resource::<Assets<Scene>>().get(scene)?.clone();
+
+    let root = world
+        .spawn((Transform::default(), GlobalTransform::default()))
+        .id();
+    spawn_nodes(world, root, scene, &loaded);
+
+    Some(root)
+}
+
+// Spawns the scene of every entity with a `Handle<Scene>` as its children once
+// the scene is loaded, runs in `PreUpdate`.
+pub fn spawn_loaded_scenes(world: &mut World) {
+    let mut pending = world.query_filtered::<(Entity, &Handle<Scene>), Without<SpawnedScene>>();
+
+    let loaded: Vec<_> = {
+        let scenes = world.resource::<Assets<Scene>>();
+
+        pending
+            .iter(world)
+            .filter_map(|(entity, scene)| Some((entity, *scene, scenes.get(*scene)?.clone())))
+            .collect()
+    };
+
+    for (entity, handle, scene) in loaded {
+        let nodes = spawn_nodes(world, entity, handle, &scene);
+        world.entity_mut(entity).insert(SpawnedScene(nodes));
+    }
+}
+
+// Updates the assets of reloaded scenes in place, so meshes and materials are
+// reloaded like any other asset. Scenes whose nodes changed are spawned again
+// under the same entities. Runs in `PreUpdate` before `spawn_loaded_scenes`.
+pub fn reload_scenes(world: &mut World, mut reader: Local<ManualEventReader<AssetEvent<Scene>>>) {
+    let modified: Vec<_> = reader
+        .read(world.resource::<Events<AssetEvent<Scene>>>())
+        .filter_map(|event| match event {
+            AssetEvent::Modified(handle) => Some(*handle),
+            AssetEvent::Loaded(_) => None,
+        })
+        .collect();
+
+    for handle in modified {
+        let scene = match world.resource::<Assets<Scene>>().get(handle) {
+            Some(scene) => scene.clone(),
+            None => continue,
+        };
+
+        let (previous, handles) = match world.resource::<LoadedScenes>().0.get(&handle) {
+            Some(loaded) => loaded.clone(),
+            None => continue,
+        };
+
+        if previous.same_layout(&scene) {
+            write_scene_assets(world, &scene, &handles);
+            world
+                .resource_mut::<LoadedScenes>()
+                .0
+                .insert(handle, (scene, handles));
+            continue;
+        }
+
+        // Spawned again with new handles, the old assets are dropped.
+        world.resource_mut::<LoadedScenes>().0.remove(&handle);
+        remove_scene_assets(world, &handles);
+
+        let mut spawned = world.query::<(Entity, &Handle<Scene>, &SpawnedScene)>();
+        let roots: Vec<_> = spawned
+            .iter(world)
+            .filter(|(_, scene, _)| **scene == handle)
+            .map(|(entity, _, spawned)| (entity, spawned.0.clone()))
+            .collect();
+
+        for (root, nodes) in roots {
+            if let Some(mut children) = world.get_mut::<Children>(root) {
+                children.0.retain(|child| !nodes.contains(child));
+            }
+
+            for node in nodes {
+                despawn_with_children(world, node);
+            }
+
+            world
+                .entity_mut(root)
+                .remove::<(SceneAnimations, AnimationTargets)>();
+
+            let nodes = spawn_nodes(world, root, handle, &scene);
+            world.entity_mut(root).insert(SpawnedScene(nodes));
+        }
+    }
+}
+
+fn despawn_with_children(world: &mut World, entity: Entity) {
+    if let Some(children) = world.get::<Children>(entity) {
+        for child in children.0.clone() {
+            despawn_with_children(world, child);
+        }
+    }
+
+    world.despawn(entity);
+}
+
+fn scene_material(world: &World, material: &MaterialData) -> SceneMaterial {
+    let server = world.resource::<AssetServer>();
+    let base_color_texture = material
+        .base_color_texture
+        .as_ref()
+        .map(|path| server.load(path.strip_prefix(server.root()).unwrap_or(path)));
+
+    SceneMaterial {
+        base_color: material.base_color,
+        base_color_texture,
+        alpha_mode: material.alpha_mode,
+        double_sided: material.double_sided,
+    }
+}
+
+// Handles of a scene's assets, added the first time it's spawned.
+fn scene_handles(world: &mut World, handle: Handle<Scene>, scene: &Arc<Scene>) -> SceneHandles {
+    if let Some((_, handles)) = world.resource::<LoadedScenes>().0.get(&handle) {
+        return handles.clone();
+    }
+
+    let meshes = {
+        let mut assets = world.resource_mut::<Assets<MeshData>>();
+        scene
+            .meshes
+            .iter()
+            .map(|mesh| assets.add(mesh.clone()))
+            .collect()
+    };
+
+    let materials: Vec<_> = scene
+        .materials
+        .iter()
+        .map(|material| scene_material(world, material))
+        .collect();
+    let materials = {
+        let mut assets = world.resource_mut::<Assets<SceneMaterial>>();
+        materials
+            .into_iter()
+            .map(|material| assets.add(material))
+            .collect()
+    };
+
+    let animations = {
+        let mut clips = world.resource_mut::<Assets<AnimationClip>>();
+        scene
+            .animations
+            .iter()
+            .map(|clip| clips.add(clip.clone()))
+            .collect()
+    };
+
+    let handles = SceneHandles {
+        meshes,
+        materials,
+        animations,
+    };
+
+    world
+        .resource_mut::<LoadedScenes>()
+        .0
+        .insert(handle, (scene.clone(), handles.clone()));
+
+    handles
+}
+
+// Replaces the assets behind `handles`, which must match the scene's counts.
+fn write_scene_assets(world: &mut World, scene: &Scene, handles: &SceneHandles) {
+    for (handle, mesh) in handles.meshes.iter().zip(&scene.meshes) {
+        world
+            .resource_mut::<Assets<MeshData>>()
+            .insert(*handle, mesh.clone());
+        world
+            .resource_mut::<Events<AssetEvent<MeshData>>>()
+            .send(AssetEvent::Modified(*handle));
+    }
+
+    for (handle, material) in handles.materials.iter().zip(&scene.materials) {
+        let material = scene_material(world, material);
+
+        world
+            .resource_mut::<Assets<SceneMaterial>>()
+            .insert(*handle, material);
+        world
+            .resource_mut::<Events<AssetEvent<SceneMaterial>>>()
+            .send(AssetEvent::Modified(*handle));
+    }
+
+    // Players look their clips up every frame.
+    for (handle, clip) in handles.animations.iter().zip(&scene.animations) {
+        world
+            .resource_mut::<Assets<AnimationClip>>()
+            .insert(*handle, clip.clone());
+    }
+}
+
+fn remove_scene_assets(world: &mut World, handles: &SceneHandles) {
+    for handle in &handles.meshes {
+        world.resource_mut::<Assets<MeshData>>().remove(*handle);
+    }
+
+    for handle in &handles.materials {
+        world
+            .resource_mut::<Assets<SceneMaterial>>()
+            .remove(*handle);
+    }
+
+    for handle in &handles.animations {
+        world
+            .resource_mut::<Assets<AnimationClip>>()
+            .remove(*handle);
+    }
+}
+
+// Spawns the scene's root nodes as children of `root`, returning them.
+fn spawn_nodes(
+    world: &mut World,
+    root: Entity,
+    handle: Handle<Scene>,
+    scene: &Arc<Scene>,
+) -> Vec<Entity> {
+    let handles = scene_handles(world, handle, scene);
+    let mut entities = vec![None; scene.nodes.len()];
+
+    let children: Vec<_> = scene
+        .roots
+        .iter()
+        .map(|node| spawn_node(world, scene, &handles, &mut entities, *node, root))
+        .collect();
+
+    // Joints may be anywhere in the scene, skins are added once every node is.
+    for (index, node) in scene.nodes.iter().enumerate() {
+        let (entity, skin) = match (entities[index], node.skin) {
+            (Some(entity), Some(skin)) => (entity, &scene.skins[skin]),
+            _ => continue,
+        };
+
+        let joints = match skin.joints.iter().map(|joint| entities[*joint]).collect() {
+            Some(joints) => joints,
+            None => {
+                log::warn!("Skin of {:?} has joints outside the scene", node.name);
+                continue;
+            }
+        };
+
+        world.entity_mut(entity).insert(Skin {
+            joints,
+            inverse_bind_matrices: skin.inverse_bind_matrices.clone(),
+        });
+    }
+
+    if !handles.animations.is_empty() {
+        world.entity_mut(root).insert((
+            SceneAnimations(handles.animations.clone()),
+            AnimationTargets(entities),
+        ));
+    }
+
+    match world.get_mut::<Children>(root) {
+        Some(mut existing) => existing.0.extend(children.iter().copied()),
+        None => {
+            world.entity_mut(root).insert(Children(children.clone()));
+        }
+    }
+
+    children
+}
+
+fn spawn_node(
+    world: &mut World,
+    scene: &Scene,
+    handles: &SceneHandles,
+    entities: &mut [Option<Entity>],
+    index: usize,
+    parent: Entity,
+) -> Entity {
+    let node = &scene.nodes[index];
+
+    let mut entity = world.spawn((
+        Transform::from_matrix(node.transform),
+        GlobalTransform::default(),
+        Parent(parent),
+    ));
+
+    if let Some(name) = &node.name {
+        entity.insert(Name(name.clone()));
+    }
+
+    if let Some(mesh) = node.mesh {
+        entity.insert(handles.meshes[mesh]);
+    }
+
+    if let Some(material) = node.material {
+        entity.insert(handles.materials[material]);
+    }
+
+    if let Some(light) = node.light {
+        entity.insert(light);
+    }
+
+    if let Some(camera) = node.camera {
+        entity.insert(camera);
+    }
+
+    if let Some(weights) = &node.weights {
+        entity.insert(MorphWeights(weights.clone()));
+    }
+
+    let entity = entity.id();
+    entities[index] = Some(entity);
+
+    let children: Vec<_> = node
+        .children
+        .iter()
+        .map(|child| spawn_node(world, scene, handles, entities, *child, entity))
+        .collect();
+
+    if !children.is_empty() {
+        world.entity_mut(entity).insert(Children(children));
+    }
+
+    entity
+}
+
+// Registers scenes and their materials, and spawns the entities with a
+// `Handle<Scene>`. Must be added after `AssetPlugin`.
+pub struct ScenePlugin;
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Scene>()
+            .add_asset::<SceneMaterial>()
+            .add_asset::<AnimationClip>()
+            .init_resource::<LoadedScenes>()
+            .add_systems(
+                PreUpdate,
+                (reload_scenes, spawn_loaded_scenes)
+                    .chain()
+                    .after(update_assets),
+            );
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use bevy_ecs::{prelude::*, schedule::ScheduleLabel};
+
+// Runs once, before the first update.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Startup;
+
+// Runs every frame before `FixedUpdate`, input and events are processed here.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PreUpdate;
+
+// Runs zero or more times per frame at the rate of `FixedTimestep`.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FixedUpdate;
+
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Update;
+
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PostUpdate;
+
+// Copies the data needed to draw the frame from the world to the renderer.
+#[derive(ScheduleLabel, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RenderExtract;
+
+// Accumulates frame time and decides how many `FixedUpdate` steps to run.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct FixedTimestep {
+    pub timestep: Duration,
+    // Steps run in a single frame at most, the remaining time is dropped so a
+    // slow frame doesn't cause more slow frames.
+    pub max_steps: u32,
+    accumulator: Duration,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::from_hz(60.0)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(timestep: Duration) -> Self {
+        Self {
+            timestep,
+            max_steps: 8,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    pub fn from_hz(hz: f64) -> Self {
+        Self::new(Duration::from_secs_f64(1.0 / hz))
+    }
+
+    pub fn accumulator(&self) -> Duration {
+        self.accumulator
+    }
+
+    // Adds the frame time and returns the number of steps to run.
+    pub fn accumulate(&mut self, delta: Duration) -> u32 {
+        if self.timestep.is_zero() {
+            return 0;
+        }
+
+        self.accumulator += delta;
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        if steps > self.max_steps {
+            log::warn!("Skipping {} fixed updates", steps - self.max_steps);
+            steps = self.max_steps;
+        }
+
+        steps
+    }
+}
@@ -0,0 +1,168 @@
+use std::time::{Duration, Instant};
+
+use bevy_ecs::prelude::*;
+
+// Frame timing, advanced at the start of every update. `delta` and `elapsed`
+// are scaled and stop while paused, the raw values always follow the clock.
+#[derive(Resource, Clone, Debug)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    raw_delta: Duration,
+    raw_elapsed: Duration,
+    frame_count: u64,
+
+    scale: f32,
+    paused: bool,
+
+    fixed_timestep: Duration,
+    fixed_alpha: f32,
+
+    // Advances by this step every update instead of reading the clock, so tests
+    // and replays get the same timings on every run.
+    manual_step: Option<Duration>,
+    last_update: Option<Instant>,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            raw_delta: Duration::ZERO,
+            raw_elapsed: Duration::ZERO,
+            frame_count: 0,
+            scale: 1.0,
+            paused: false,
+            fixed_timestep: Duration::ZERO,
+            fixed_alpha: 0.0,
+            manual_step: None,
+            last_update: None,
+        }
+    }
+}
+
+impl Time {
+    pub fn manual(step: Duration) -> Self {
+        Self {
+            manual_step: Some(step),
+            ..Default::default()
+        }
+    }
+
+    // Time since the previous update, scaled; zero on the first update unless
+    // stepped manually.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn raw_delta(&self) -> Duration {
+        self.raw_delta
+    }
+
+    pub fn raw_delta_seconds(&self) -> f32 {
+        self.raw_delta.as_secs_f32()
+    }
+
+    pub fn raw_elapsed(&self) -> Duration {
+        self.raw_elapsed
+    }
+
+    // Updates run so far, including the current one.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    // Duration of a `FixedUpdate` step.
+    pub fn fixed_delta(&self) -> Duration {
+        self.fixed_timestep
+    }
+
+    // How far the frame is between the last fixed step and the next, from 0 to 1.
+    // Used to interpolate state simulated in `FixedUpdate`.
+    pub fn fixed_alpha(&self) -> f32 {
+        self.fixed_alpha
+    }
+
+    pub fn manual_step(&self) -> Option<Duration> {
+        self.manual_step
+    }
+
+    // None goes back to reading the clock.
+    pub fn set_manual_step(&mut self, step: Option<Duration>) {
+        self.manual_step = step;
+        self.last_update = None;
+    }
+
+    // Starts a new frame, measuring the time since the previous one.
+    pub fn update(&mut self) {
+        let raw_delta = match self.manual_step {
+            Some(step) => step,
+            None => {
+                let now = Instant::now();
+                let raw_delta = match self.last_update {
+                    Some(last_update) => now.duration_since(last_update),
+                    None => Duration::ZERO,
+                };
+                self.last_update = Some(now);
+                raw_delta
+            }
+        };
+
+        self.advance(raw_delta);
+    }
+
+    // Starts a new frame `raw_delta` after the previous one.
+    pub fn advance(&mut self, raw_delta: Duration) {
+        self.raw_delta = raw_delta;
+        self.raw_elapsed += raw_delta;
+
+        self.delta = match self.paused {
+            true => Duration::ZERO,
+            false => raw_delta.mul_f32(self.scale),
+        };
+        self.elapsed += self.delta;
+
+        self.frame_count += 1;
+    }
+
+    pub(crate) fn set_fixed(&mut self, timestep: Duration, accumulator: Duration) {
+        self.fixed_timestep = timestep;
+        self.fixed_alpha = match timestep.is_zero() {
+            true => 0.0,
+            false => (accumulator.as_secs_f32() / timestep.as_secs_f32()).min(1.0),
+        };
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+
+use bevy_ecs::{entity::Entities, prelude::*};
+use bevy_math::prelude::*;
+
+use crate::{plugin::Plugin, schedule::PostUpdate, AppBuilder};
+
+// Position of an entity relative to its parent, or to the world without one.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::IDENTITY
+        }
+    }
+
+    // Shear is lost, matrices made of translation, rotation and scale round-trip.
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+// World space matrix of an entity, computed from its `Transform` and those of
+// its ancestors in `PostUpdate`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct GlobalTransform(pub Mat4);
+
+impl Default for GlobalTransform {
+    fn default() -> Self {
+        Self(Mat4::IDENTITY)
+    }
+}
+
+impl GlobalTransform {
+    pub fn translation(&self) -> Vec3 {
+        self.0.w_axis.truncate()
+    }
+}
+
+// Kept in sync with the parent's `Children` by `set_parent`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Children(pub Vec<Entity>);
+
+// Moves `child` under `parent`, or makes it a root with None, updating both
+// sides of the hierarchy. Parenting an entity to itself or to one of its
+// descendants is ignored.
+pub fn set_parent(world: &mut World, child: Entity, parent: Option<Entity>) {
+    if let Some(parent) = parent {
+        let mut ancestor = Some(parent);
+        while let Some(entity) = ancestor {
+            if entity == child {
+                log::warn!("Can't parent {:?} to its descendant {:?}", child, parent);
+                return;
+            }
+
+            ancestor = world.get::<Parent>(entity).map(|parent| parent.0);
+        }
+    }
+
+    if let Some(Parent(previous)) = world.get::<Parent>(child).copied() {
+        if let Some(mut children) = world.get_mut::<Children>(previous) {
+            children.0.retain(|entity| *entity != child);
+        }
+    }
+
+    let parent = match parent {
+        Some(parent) => parent,
+        None => {
+            world.entity_mut(child).remove::<Parent>();
+            return;
+        }
+    };
+
+    world.entity_mut(child).insert(Parent(parent));
+
+    match world.get_mut::<Children>(parent) {
+        Some(mut children) => children.0.push(child),
+        None => {
+            world.entity_mut(parent).insert(Children(vec![child]));
+        }
+    }
+}
+
+// Computes `GlobalTransform` of every entity with a `Transform`.
+pub struct TransformPlugin;
+
+impl Plugin for TransformPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_systems(PostUpdate, propagate_transforms);
+    }
+}
+
+// Walks the hierarchy from its roots but only recomputes the entities whose
+// `Transform` or `Parent` changed since the last run, along with everything
+// below them. The others keep their `GlobalTransform` untouched, so
+// `Changed<GlobalTransform>` only matches entities that actually moved.
+pub fn propagate_transforms(
+    roots: Query<(Entity, Ref<Transform>), Without<Parent>>,
+    nodes: Query<(Entity, Ref<Transform>, Ref<Parent>)>,
+    children: Query<&Children>,
+    entities: &Entities,
+    mut globals: Query<&mut GlobalTransform>,
+    mut orphaned: RemovedComponents<Parent>,
+    mut dangling: Local<HashSet<Entity>>,
+) {
+    // Entities that lost their parent became roots without changing.
+    let orphaned: HashSet<_> = orphaned.read().collect();
+
+    for (root, transform) in &roots {
+        let changed = transform.is_changed() || orphaned.contains(&root);
+
+        propagate(
+            root,
+            Mat4::IDENTITY,
+            &transform,
+            changed,
+            &nodes,
+            &children,
+            &mut globals,
+        );
+    }
+
+    // Children of a parent despawned without them keep a `Parent` to a dead
+    // entity and are roots too, recomputed once when their parent goes away.
+    let previously_dangling = std::mem::take(&mut *dangling);
+
+    for (entity, transform, parent) in &nodes {
+        if entities.contains(parent.0) {
+            continue;
+        }
+
+        let changed =
+            transform.is_changed() || parent.is_changed() || !previously_dangling.contains(&entity);
+        dangling.insert(entity);
+
+        propagate(
+            entity,
+            Mat4::IDENTITY,
+            &transform,
+            changed,
+            &nodes,
+            &children,
+            &mut globals,
+        );
+    }
+}
+
+fn propagate(
+    entity: Entity,
+    parent_matrix: Mat4,
+    transform: &Transform,
+    changed: bool,
+    nodes: &Query<(Entity, Ref<Transform>, Ref<Parent>)>,
+    children: &Query<&Children>,
+    globals: &mut Query<&mut GlobalTransform>,
+) {
+    let matrix = match globals.get_mut(entity) {
+        Ok(global) if !changed => global.0,
+        Ok(mut global) => {
+            global.0 = parent_matrix * transform.compute_matrix();
+            global.0
+        }
+        Err(_) => parent_matrix * transform.compute_matrix(),
+    };
+
+    for child in children
+        .get(entity)
+        .into_iter()
+        .flat_map(|children| &children.0)
+    {
+        let (_, transform, parent) = match nodes.get(*child) {
+            Ok(node) => node,
+            Err(_) => continue,
+        };
+
+        // Stale entries are skipped, the child is reached from its real parent.
+        if parent.0 != entity {
+            continue;
+        }
+
+        let changed = changed || transform.is_changed() || parent.is_changed();
+
+        propagate(
+            *child, matrix, &transform, changed, nodes, children, globals,
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+// Reports changes to a set of files. Their directories are watched rather than
+// the files themselves, since editors often save by replacing the file.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+
+    // Canonical path to the path the file was watched with.
+    files: HashMap<PathBuf, PathBuf>,
+    directories: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new() -> notify::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+
+        let watcher = notify::recommended_watcher(move |event| {
+            // The receiver only goes away with the watcher.
+            let _ = sender.send(event);
+        })?;
+
+        Ok(Self {
+            watcher,
+            receiver,
+            files: HashMap::new(),
+            directories: HashSet::new(),
+        })
+    }
+
+    pub fn watch(&mut self, path: &Path) {
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(err) => {
+                log::warn!("Can't watch {}: {}", path.display(), err);
+                return;
+            }
+        };
+
+        if let Some(directory) = canonical.parent() {
+            if !self.directories.contains(directory) {
+                match self.watcher.watch(directory, RecursiveMode::NonRecursive) {
+                    Ok(()) => {
+                        self.directories.insert(directory.to_path_buf());
+                    }
+                    Err(err) => log::warn!("Can't watch {}: {}", directory.display(), err),
+                }
+            }
+        }
+
+        self.files.insert(canonical, path.to_path_buf());
+    }
+
+    // Watched files changed since the last call, each reported once, as they
+    // were passed to `watch`.
+    pub fn changed(&self) -> Vec<PathBuf> {
+        let mut changed = vec![];
+
+        for event in self.receiver.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(err) => {
+                    log::warn!("File watcher error: {}", err);
+                    continue;
+                }
+            };
+
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+
+            for path in event.paths {
+                if let Some(path) = self.files.get(&path) {
+                    if !changed.contains(path) {
+                        changed.push(path.clone());
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+}
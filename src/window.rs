@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use bevy_ecs::prelude::*;
+use winit::{
+    dpi::LogicalSize,
+    event_loop::EventLoop,
+    monitor::{MonitorHandle, VideoMode},
+    window::{CursorGrabMode, Fullscreen, Window, WindowBuilder, WindowId},
+};
+
+use crate::{rendering::renderer::Renderer, AppBuilder};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    // Covers the current monitor without changing its video mode.
+    BorderlessFullscreen,
+    // Switches the current monitor to its largest video mode.
+    Fullscreen,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CursorGrab {
+    #[default]
+    None,
+    // Kept inside the window.
+    Confined,
+    // Kept in place, only motion is reported.
+    Locked,
+}
+
+// Requested presentation mode, falls back to the closest supported one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    // Vsync, always supported.
+    #[default]
+    Fifo,
+    // Vsync without blocking, falls back to Fifo.
+    Mailbox,
+    // No vsync, may tear. Falls back to Mailbox then Fifo.
+    Immediate,
+}
+
+impl PresentMode {
+    pub fn select(self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        let candidates: &[wgpu::PresentMode] = match self {
+            PresentMode::Fifo => &[wgpu::PresentMode::Fifo],
+            PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Fifo],
+            PresentMode::Immediate => &[
+                wgpu::PresentMode::Immediate,
+                wgpu::PresentMode::Mailbox,
+                wgpu::PresentMode::Fifo,
+            ],
+        };
+
+        let mode = candidates
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(wgpu::PresentMode::Fifo);
+
+        if mode != candidates[0] {
+            log::warn!("{:?} is not supported, using {:?}", candidates[0], mode);
+        }
+
+        mode
+    }
+}
+
+// Opens another window sharing the renderer's device, cameras draw to it with
+// `RenderTarget::Window`. Must be called after adding `RenderPlugin`; unlike the
+// primary window, its settings can't be changed afterwards.
+pub fn open_window(app: &mut AppBuilder, settings: &WindowSettings) -> WindowId {
+    let window = settings.create_window(app.event_loop());
+
+    app.world_mut()
+        .get_non_send_resource_mut::<Renderer>()
+        .expect("open_window requires RenderPlugin")
+        .add_window(window, settings.present_mode)
+}
+
+// Settings of the main window. Read by `RenderPlugin` when creating the window,
+// so insert it before adding the plugin; changes made later are applied at the
+// end of the frame.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct WindowSettings {
+    pub title: String,
+    // Requested inner size in logical pixels, resizing the window by hand does
+    // not update it.
+    pub width: u32,
+    pub height: u32,
+    pub min_size: Option<(u32, u32)>,
+    pub resizable: bool,
+    pub mode: WindowMode,
+    pub present_mode: PresentMode,
+    pub cursor_visible: bool,
+    pub cursor_grab: CursorGrab,
+    // Frames per second at most, None renders as fast as the present mode allows.
+    pub frame_rate_limit: Option<f32>,
+}
+
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            title: "bismuth".to_string(),
+            width: 1280,
+            height: 720,
+            min_size: None,
+            resizable: true,
+            mode: WindowMode::Windowed,
+            present_mode: PresentMode::Fifo,
+            cursor_visible: true,
+            cursor_grab: CursorGrab::None,
+            frame_rate_limit: None,
+        }
+    }
+}
+
+impl WindowSettings {
+    pub fn frame_time(&self) -> Option<Duration> {
+        self.frame_rate_limit
+            .filter(|limit| *limit > 0.0)
+            .map(|limit| Duration::from_secs_f32(1.0 / limit))
+    }
+
+    pub fn create_window(&self, event_loop: &EventLoop<()>) -> Window {
+        let mut builder = WindowBuilder::new()
+            .with_title(&self.title)
+            .with_inner_size(LogicalSize::new(self.width, self.height))
+            .with_resizable(self.resizable)
+            .with_fullscreen(fullscreen(self.mode, event_loop.primary_monitor()));
+
+        if let Some((width, height)) = self.min_size {
+            builder = builder.with_min_inner_size(LogicalSize::new(width, height));
+        }
+
+        let window = builder.build(event_loop).unwrap();
+        window.set_cursor_visible(self.cursor_visible);
+        set_cursor_grab(&window, self.cursor_grab);
+
+        window
+    }
+
+    // Applies the fields that differ from `previous` to the window.
+    pub fn apply(&self, previous: &WindowSettings, renderer: &mut Renderer) {
+        let window = renderer.get_window();
+
+        if self.title != previous.title {
+            window.set_title(&self.title);
+        }
+
+        if (self.width, self.height) != (previous.width, previous.height) {
+            window.set_inner_size(LogicalSize::new(self.width, self.height));
+        }
+
+        if self.min_size != previous.min_size {
+            window.set_min_inner_size(
+                self.min_size
+                    .map(|(width, height)| LogicalSize::new(width, height)),
+            );
+        }
+
+        if self.resizable != previous.resizable {
+            window.set_resizable(self.resizable);
+        }
+
+        if self.mode != previous.mode {
+            window.set_fullscreen(fullscreen(self.mode, window.current_monitor()));
+        }
+
+        if self.cursor_visible != previous.cursor_visible {
+            window.set_cursor_visible(self.cursor_visible);
+        }
+
+        if self.cursor_grab != previous.cursor_grab {
+            set_cursor_grab(window, self.cursor_grab);
+        }
+
+        if self.present_mode != previous.present_mode {
+            renderer.set_present_mode(renderer.primary_window(), self.present_mode);
+        }
+    }
+}
+
+fn fullscreen(mode: WindowMode, monitor: Option<MonitorHandle>) -> Option<Fullscreen> {
+    match mode {
+        WindowMode::Windowed => None,
+        WindowMode::BorderlessFullscreen => Some(Fullscreen::Borderless(monitor)),
+        WindowMode::Fullscreen => match monitor.as_ref().and_then(largest_video_mode) {
+            Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+            None => {
+                log::warn!("No video mode available, using borderless fullscreen");
+                Some(Fullscreen::Borderless(monitor))
+            }
+        },
+    }
+}
+
+fn largest_video_mode(monitor: &MonitorHandle) -> Option<VideoMode> {
+    monitor.video_modes().max_by_key(|mode| {
+        let size = mode.size();
+        (size.width * size.height, mode.refresh_rate_millihertz())
+    })
+}
+
+// Platforms support either confining or locking, so fall back to the other one.
+fn set_cursor_grab(window: &Window, grab: CursorGrab) {
+    let result = match grab {
+        CursorGrab::None => window.set_cursor_grab(CursorGrabMode::None),
+        CursorGrab::Confined => window
+            .set_cursor_grab(CursorGrabMode::Confined)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked)),
+        CursorGrab::Locked => window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined)),
+    };
+
+    if let Err(err) = result {
+        log::warn!("Failed to grab cursor: {}", err);
+    }
+}
+
+// Settings the primary window currently has, starting with the ones it was
+// created from. Inserted by `RenderPlugin` along with the window.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct AppliedWindowSettings(pub WindowSettings);
+
+// Applies runtime changes to `WindowSettings`, runs in `PostUpdate`.
+pub fn apply_window_settings(
+    settings: Res<WindowSettings>,
+    mut renderer: NonSendMut<Renderer>,
+    mut applied: ResMut<AppliedWindowSettings>,
+) {
+    if !settings.is_changed() || *settings == applied.0 {
+        return;
+    }
+
+    settings.apply(&applied.0, &mut renderer);
+    applied.0 = settings.clone();
+}
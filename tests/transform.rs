@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use bevy_ecs::prelude::*;
+use bevy_math::prelude::*;
+use bismuth::transform::{
+    propagate_transforms, set_parent, Children, GlobalTransform, Parent, Transform,
+};
+use proptest::prelude::*;
+
+// Entities whose `GlobalTransform` changed during the last update.
+#[derive(Resource, Default)]
+struct Moved(HashSet<Entity>);
+
+fn record_moved(moved: Query<Entity, Changed<GlobalTransform>>, mut out: ResMut<Moved>) {
+    out.0 = moved.iter().collect();
+}
+
+struct Hierarchy {
+    world: World,
+    schedule: Schedule,
+    entities: Vec<Entity>,
+}
+
+impl Hierarchy {
+    fn new(count: usize) -> Self {
+        let mut world = World::new();
+        world.init_resource::<Moved>();
+
+        let entities = (0..count)
+            .map(|_| {
+                world
+                    .spawn((Transform::default(), GlobalTransform::default()))
+                    .id()
+            })
+            .collect();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((
+            propagate_transforms,
+            record_moved.after(propagate_transforms),
+        ));
+
+        Self {
+            world,
+            schedule,
+            entities,
+        }
+    }
+
+    fn update(&mut self) {
+        self.schedule.run(&mut self.world);
+    }
+
+    fn moved(&self) -> &HashSet<Entity> {
+        &self.world.resource::<Moved>().0
+    }
+
+    fn set_transform(&mut self, entity: Entity, transform: Transform) {
+        *self.world.get_mut::<Transform>(entity).unwrap() = transform;
+    }
+
+    // Recomputes the world matrix from the chain of parents, up to the first
+    // one that was despawned.
+    fn expected(&self, entity: Entity) -> Mat4 {
+        let mut chain = vec![entity];
+        while let Some(parent) = self.world.get::<Parent>(*chain.last().unwrap()) {
+            if self.world.get_entity(parent.0).is_none() {
+                break;
+            }
+            chain.push(parent.0);
+        }
+
+        chain.iter().rev().fold(Mat4::IDENTITY, |matrix, entity| {
+            matrix
+                * self
+                    .world
+                    .get::<Transform>(*entity)
+                    .unwrap()
+                    .compute_matrix()
+        })
+    }
+
+    fn descendants(&self, entity: Entity) -> HashSet<Entity> {
+        let mut found = HashSet::from([entity]);
+        let mut stack = vec![entity];
+
+        while let Some(entity) = stack.pop() {
+            if let Some(children) = self.world.get::<Children>(entity) {
+                stack.extend(&children.0);
+                found.extend(&children.0);
+            }
+        }
+
+        found
+    }
+
+    fn check(&self) {
+        for entity in &self.entities {
+            let global = self.world.get::<GlobalTransform>(*entity).unwrap().0;
+            let expected = self.expected(*entity);
+
+            assert!(
+                global.abs_diff_eq(expected, 1e-3),
+                "{:?}: {:?} != {:?}",
+                entity,
+                global,
+                expected
+            );
+
+            let parent = self.world.get::<Parent>(*entity);
+            if let Some(children) = parent.and_then(|parent| self.world.get::<Children>(parent.0)) {
+                assert_eq!(children.0.iter().filter(|c| *c == entity).count(), 1);
+            }
+
+            for child in self
+                .world
+                .get::<Children>(*entity)
+                .into_iter()
+                .flat_map(|c| &c.0)
+                .filter(|child| self.world.get_entity(**child).is_some())
+            {
+                assert_eq!(self.world.get::<Parent>(*child), Some(&Parent(*entity)));
+            }
+        }
+    }
+}
+
+fn transform() -> impl Strategy<Value = Transform> {
+    let component = -5.0f32..5.0;
+    (
+        (component.clone(), component.clone(), component),
+        (
+            -1.0f32..1.0,
+            -1.0f32..1.0,
+            -1.0f32..1.0,
+            0.0f32..std::f32::consts::TAU,
+        ),
+        0.8f32..1.25,
+    )
+        .prop_map(|((x, y, z), (ax, ay, az, angle), scale)| Transform {
+            translation: Vec3::new(x, y, z),
+            rotation: Quat::from_axis_angle(
+                Vec3::new(ax, ay, az).try_normalize().unwrap_or(Vec3::Y),
+                angle,
+            ),
+            scale: Vec3::splat(scale),
+        })
+}
+
+proptest! {
+    #[test]
+    fn deep_chains_match_the_product_of_local_transforms(
+        transforms in prop::collection::vec(transform(), 1..64),
+        edited in any::<prop::sample::Index>(),
+        edit in transform(),
+    ) {
+        let mut hierarchy = Hierarchy::new(transforms.len());
+        let entities = hierarchy.entities.clone();
+
+        for (i, transform) in transforms.iter().enumerate() {
+            hierarchy.set_transform(entities[i], *transform);
+            if i > 0 {
+                set_parent(&mut hierarchy.world, entities[i], Some(entities[i - 1]));
+            }
+        }
+
+        hierarchy.update();
+        hierarchy.check();
+
+        // Nothing changed, nothing is recomputed.
+        hierarchy.update();
+        prop_assert!(hierarchy.moved().is_empty());
+
+        // Only the edited entity and the ones below it move.
+        let edited = edited.index(entities.len());
+        hierarchy.set_transform(entities[edited], edit);
+        hierarchy.update();
+        hierarchy.check();
+
+        let moved: HashSet<_> = entities[edited..].iter().copied().collect();
+        prop_assert_eq!(hierarchy.moved(), &moved);
+    }
+
+    #[test]
+    fn children_of_despawned_parents_become_roots(
+        transforms in prop::collection::vec(transform(), 2..32),
+        despawned in any::<prop::sample::Index>(),
+        edit in transform(),
+    ) {
+        let mut hierarchy = Hierarchy::new(transforms.len());
+        let entities = hierarchy.entities.clone();
+
+        for (i, transform) in transforms.iter().enumerate() {
+            hierarchy.set_transform(entities[i], *transform);
+            if i > 0 {
+                set_parent(&mut hierarchy.world, entities[i], Some(entities[i - 1]));
+            }
+        }
+
+        hierarchy.update();
+
+        // Despawned alone, its child keeps a `Parent` to the dead entity.
+        let despawned = despawned.index(entities.len() - 1);
+        hierarchy.world.despawn(entities[despawned]);
+        hierarchy.entities.remove(despawned);
+
+        hierarchy.update();
+        hierarchy.check();
+
+        let moved: HashSet<_> = entities[despawned + 1..].iter().copied().collect();
+        prop_assert_eq!(hierarchy.moved(), &moved);
+
+        // It's recomputed once, then only when it changes.
+        hierarchy.update();
+        prop_assert!(hierarchy.moved().is_empty());
+
+        let orphan = entities[despawned + 1];
+        hierarchy.set_transform(orphan, edit);
+        hierarchy.update();
+        hierarchy.check();
+        prop_assert_eq!(hierarchy.moved(), &moved);
+    }
+
+    #[test]
+    fn reparenting_keeps_world_matrices_in_sync(
+        transforms in prop::collection::vec(transform(), 2..32),
+        operations in prop::collection::vec(
+            (any::<prop::sample::Index>(), prop::option::of(any::<prop::sample::Index>()), transform()),
+            1..48,
+        ),
+    ) {
+        let mut hierarchy = Hierarchy::new(transforms.len());
+        let entities = hierarchy.entities.clone();
+
+        for (entity, transform) in entities.iter().zip(&transforms) {
+            hierarchy.set_transform(*entity, *transform);
+        }
+
+        hierarchy.update();
+        hierarchy.check();
+
+        for (child, parent, transform) in operations {
+            let child = entities[child.index(entities.len())];
+            let parent = parent.map(|parent| entities[parent.index(entities.len())]);
+
+            // Cycles are refused, the hierarchy stays as it was.
+            let refused = parent.is_some_and(|parent| hierarchy.descendants(child).contains(&parent));
+            let previous = hierarchy.world.get::<Parent>(child).copied();
+
+            set_parent(&mut hierarchy.world, child, parent);
+
+            let current = hierarchy.world.get::<Parent>(child).copied();
+            if refused {
+                prop_assert_eq!(current, previous);
+            } else {
+                prop_assert_eq!(current, parent.map(Parent));
+            }
+
+            // Children also get a new local transform.
+            if hierarchy.world.get::<Parent>(child).is_some() {
+                hierarchy.set_transform(child, transform);
+            }
+
+            hierarchy.update();
+            hierarchy.check();
+
+            // An entity moves with the subtree it was added to.
+            if !refused && current != previous {
+                prop_assert!(hierarchy
+                    .descendants(child)
+                    .is_subset(hierarchy.moved()));
+            }
+        }
+    }
+}